@@ -0,0 +1,111 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use std::cell::OnceCell;
+use ttfhe::client_key::ClientKey;
+use ttfhe::ggsw::{cmux, compute_bsk, GgswCiphertext};
+use ttfhe::glwe::GlweCiphertext;
+use ttfhe::lwe::compute_ksk;
+use ttfhe::params::{Params, PRESETS};
+use ttfhe::poly::ResiduePoly;
+use ttfhe::server_key::ServerKey;
+use ttfhe::utils::encode;
+
+/// Keys and ciphertexts shared by the benchmarks of a preset.
+struct Fixtures<const N: usize> {
+    ck: ClientKey<N>,
+    sk: ServerKey<N>,
+    ggsw: GgswCiphertext<N>,
+    ct1: GlweCiphertext<N>,
+    ct2: GlweCiphertext<N>,
+}
+
+impl<const N: usize> Fixtures<N> {
+    fn new(params: Params) -> Self {
+        let ck = ClientKey::<N>::new(params);
+        let sk = ServerKey::new(&ck);
+        let ggsw = GgswCiphertext::encrypt(1, &ck.glwe_sk, params.glwe_std_dev, &params.pbs);
+        let ct1 = GlweCiphertext::encrypt(encode(1, &params), &ck.glwe_sk, params.glwe_std_dev);
+        let ct2 = GlweCiphertext::encrypt(encode(2, &params), &ck.glwe_sk, params.glwe_std_dev);
+
+        Fixtures {
+            ck,
+            sk,
+            ggsw,
+            ct1,
+            ct2,
+        }
+    }
+}
+
+/// Benchmarks the core primitives with the parameters of one preset, grouped under its name.
+/// Keys are only generated if a benchmark of the group is selected.
+fn bench_preset<const N: usize>(c: &mut Criterion, name: &str, params: Params) {
+    let fixtures = OnceCell::new();
+    let fixtures = || fixtures.get_or_init(|| Fixtures::<N>::new(params));
+
+    let mut group = c.benchmark_group(name);
+    // blind rotations and key generations take up to seconds with the larger presets
+    group.sample_size(10);
+
+    let p = ResiduePoly::<N>::get_random();
+    let q = ResiduePoly::<N>::get_random();
+    group.bench_function("poly_mul", |b| b.iter(|| black_box(&p) * black_box(&q)));
+    group.bench_function("multiply_by_monomial", |b| {
+        b.iter(|| black_box(&p).multiply_by_monomial(black_box(N + 3)))
+    });
+
+    group.bench_function("external_product", |b| {
+        let f = fixtures();
+        b.iter(|| f.ggsw.external_product(black_box(&f.ct1)))
+    });
+    group.bench_function("cmux", |b| {
+        let f = fixtures();
+        b.iter(|| cmux(&f.ggsw, black_box(&f.ct1), black_box(&f.ct2)))
+    });
+
+    let lut = GlweCiphertext::trivial_encrypt_lut_poly(&params);
+    group.bench_function("blind_rotate", |b| {
+        let f = fixtures();
+        let ct = f.ck.encrypt(1);
+        b.iter(|| f.sk.blind_rotate(black_box(&ct), &lut))
+    });
+
+    group.bench_function("keyswitch", |b| {
+        let f = fixtures();
+        // encrypted under the recoded GLWE key, as output by a blind rotation
+        let extracted = f.ct1.sample_extract();
+        b.iter(|| black_box(&extracted).keyswitch(&f.sk.ksk))
+    });
+
+    group.bench_function("pbs", |b| {
+        let f = fixtures();
+        let ct = f.ck.encrypt(1);
+        b.iter(|| f.sk.bootstrap(black_box(&ct)))
+    });
+
+    group.bench_function("compute_bsk", |b| {
+        let f = fixtures();
+        b.iter(|| compute_bsk(&f.ck.lwe_sk, &f.ck.glwe_sk, &params))
+    });
+    group.bench_function("compute_ksk", |b| {
+        let f = fixtures();
+        let recoded_sk = f.ck.glwe_sk.recode();
+        b.iter(|| compute_ksk::<u64>(&recoded_sk, &f.ck.lwe_sk, &params))
+    });
+
+    group.finish();
+}
+
+fn bench_presets(c: &mut Criterion) {
+    for (name, params) in PRESETS {
+        match params.poly_size {
+            256 => bench_preset::<256>(c, name, params),
+            512 => bench_preset::<512>(c, name, params),
+            1024 => bench_preset::<1024>(c, name, params),
+            2048 => bench_preset::<2048>(c, name, params),
+            n => panic!("no bench instantiation for poly_size {n} (preset {name})"),
+        }
+    }
+}
+
+criterion_group!(benches, bench_presets);
+criterion_main!(benches);
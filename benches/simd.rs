@@ -0,0 +1,54 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use ttfhe::params::MESSAGE_3;
+use ttfhe::poly::ResiduePoly;
+use ttfhe::simd::Backend;
+use ttfhe::torus::UnsignedTorus;
+
+const LEN: usize = 1024;
+
+/// Compares every backend supported by the CPU on the kernels of the polynomial and LWE arithmetic.
+fn bench_kernels(c: &mut Criterion) {
+    let a: Vec<u64> = (0..LEN).map(|_| u64::random()).collect();
+    let b: Vec<u64> = (0..LEN).map(|_| u64::random()).collect();
+    let s = u64::random();
+    let poly = ResiduePoly::<LEN>::get_random();
+    let b32: Vec<u32> = (0..LEN).map(|_| u32::random()).collect();
+    let s32 = u32::random();
+
+    for backend in Backend::available() {
+        let name = backend.name();
+        let mut acc = a.clone();
+
+        c.bench_with_input(BenchmarkId::new("add_assign", name), &b, |bench, b| {
+            bench.iter(|| backend.add_assign(black_box(&mut acc), b))
+        });
+        c.bench_with_input(BenchmarkId::new("neg_assign", name), &b, |bench, _| {
+            bench.iter(|| backend.neg_assign(black_box(&mut acc)))
+        });
+        c.bench_with_input(BenchmarkId::new("add_scaled", name), &b, |bench, b| {
+            bench.iter(|| backend.add_scaled(black_box(&mut acc), b, s))
+        });
+        c.bench_with_input(BenchmarkId::new("dot_product", name), &b, |bench, b| {
+            bench.iter(|| backend.dot_product(black_box(&a), b))
+        });
+
+        let mut acc32 = b32.clone();
+        c.bench_with_input(
+            BenchmarkId::new("add_scaled_u32", name),
+            &b32,
+            |bench, b| bench.iter(|| backend.add_scaled_u32(black_box(&mut acc32), b, s32)),
+        );
+
+        let mut res = vec![ResiduePoly::<LEN>::default(); MESSAGE_3.pbs.level];
+        c.bench_with_input(
+            BenchmarkId::new("decompose_poly", name),
+            &poly,
+            |bench, poly| {
+                bench.iter(|| backend.decompose_poly(black_box(poly), &MESSAGE_3.pbs, &mut res))
+            },
+        );
+    }
+}
+
+criterion_group!(benches, bench_kernels);
+criterion_main!(benches);
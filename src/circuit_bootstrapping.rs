@@ -0,0 +1,121 @@
+use crate::client_key::ClientKey;
+use crate::functional_ks::{
+    compute_private_functional_ksk, private_functional_keyswitch, PrivateFunctionalKeySwitchKey,
+};
+use crate::ggsw::GgswCiphertext;
+use crate::glwe::GlweCiphertext;
+use crate::lwe::LweCiphertext;
+use crate::params::{CircuitBootstrappingParams, DecompositionParams, Params};
+use crate::poly::ResiduePoly;
+use crate::server_key::ServerKey;
+use crate::utils::gadget;
+use serde::{Deserialize, Serialize};
+
+/// Private functional keyswitching keys turning LWE encryptions of `m` under the recoded GLWE key
+/// into GLWE encryptions of `-S_u * m` for every polynomial `S_u` of the GLWE key, and of `m`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CircuitBootstrappingKey<const N: usize> {
+    pub cbs: DecompositionParams,
+    pub pfksks: Vec<PrivateFunctionalKeySwitchKey<N>>,
+}
+
+impl<const N: usize> CircuitBootstrappingKey<N> {
+    pub fn new(ck: &ClientKey<N>, cbs_params: CircuitBootstrappingParams) -> Self {
+        let params = Params {
+            ks: cbs_params.pfks,
+            ..ck.params
+        };
+        let recoded_sk = ck.glwe_sk.recode();
+
+        let mut pfksks: Vec<PrivateFunctionalKeySwitchKey<N>> = ck
+            .glwe_sk
+            .polys
+            .iter()
+            .map(|s_u| {
+                compute_private_functional_ksk(&recoded_sk, &ck.glwe_sk, &params, 1, |x| {
+                    -(s_u * x[0])
+                })
+            })
+            .collect();
+
+        pfksks.push(compute_private_functional_ksk(
+            &recoded_sk,
+            &ck.glwe_sk,
+            &params,
+            1,
+            |x| {
+                let mut res = ResiduePoly::default();
+                res.coefs[0] = x[0];
+                res
+            },
+        ));
+
+        CircuitBootstrappingKey {
+            cbs: cbs_params.cbs,
+            pfksks,
+        }
+    }
+}
+
+impl<const N: usize> ServerKey<N> {
+    /// Turns a LWE encryption of a bit (encoded as a message of `params`) into a GGSW encryption
+    /// of the same bit under the GLWE key, with the decomposition of `cbsk`.
+    ///
+    /// One PBS per level outputs an encryption of `m * q/B^{j+1}`, which is then keyswitched into every row of level `j`.
+    pub fn circuit_bootstrap(
+        &self,
+        ct: &LweCiphertext,
+        cbsk: &CircuitBootstrappingKey<N>,
+    ) -> GgswCiphertext<N> {
+        let level = cbsk.cbs.level;
+        let mut z_m_gt =
+            vec![GlweCiphertext::zero(self.params.glwe_dim); cbsk.pfksks.len() * level];
+
+        for j in 0..level {
+            let g = gadget(j, &cbsk.cbs);
+            let lut = GlweCiphertext::trivial_encrypt_lut(&self.params, |m| m.wrapping_mul(g));
+            let extracted = self.blind_rotate(ct, &lut).sample_extract();
+
+            for (u, pfksk) in cbsk.pfksks.iter().enumerate() {
+                z_m_gt[u * level + j] =
+                    private_functional_keyswitch(std::slice::from_ref(&extracted), pfksk);
+            }
+        }
+
+        GgswCiphertext {
+            z_m_gt,
+            decomp: cbsk.cbs,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::circuit_bootstrapping::CircuitBootstrappingKey;
+    use crate::client_key::ClientKey;
+    use crate::ggsw::cmux;
+    use crate::glwe::GlweCiphertext;
+    use crate::params::{FAST_TEST_INSECURE, FAST_TEST_INSECURE_CBS};
+    use crate::server_key::ServerKey;
+    use crate::utils::{decode, encode};
+
+    #[test]
+    fn test_circuit_bootstrap() {
+        let ck = ClientKey::<256>::new(FAST_TEST_INSECURE);
+        let sk = ServerKey::new(&ck);
+        let cbsk = CircuitBootstrappingKey::new(&ck, FAST_TEST_INSECURE_CBS);
+        let params = ck.params;
+
+        let ct1 = GlweCiphertext::encrypt(encode(3, &params), &ck.glwe_sk, params.glwe_std_dev);
+        let ct2 = GlweCiphertext::encrypt(encode(5, &params), &ck.glwe_sk, params.glwe_std_dev);
+
+        for b in [0, 1, 1, 0] {
+            let ggsw = sk.circuit_bootstrap(&ck.encrypt(b), &cbsk);
+            assert_eq!(ggsw.clone().decrypt(&ck.glwe_sk), b as i64);
+
+            let res = cmux(&ggsw, &ct1, &ct2);
+            let pt = decode(res.decrypt(&ck.glwe_sk), &params);
+            assert_eq!(pt, if b == 0 { 3 } else { 5 });
+        }
+    }
+}
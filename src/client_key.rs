@@ -0,0 +1,60 @@
+use crate::glwe::{keygen, GlweCiphertext, SecretKey};
+use crate::lwe::{lwe_keygen, LweCiphertext, LweSecretKey};
+use crate::packing::{compute_packing_ksk, PackingKeySwitchKey};
+use crate::params::{Params, PbsOrder};
+use crate::utils::{decode, decode_poly, encode};
+
+/// Secret keys of the client, generated for a given parameter set.
+/// The degree `N` must match `params.poly_size`.
+#[derive(Clone)]
+pub struct ClientKey<const N: usize> {
+    pub params: Params,
+    pub lwe_sk: LweSecretKey,
+    pub glwe_sk: SecretKey<N>,
+}
+
+impl<const N: usize> ClientKey<N> {
+    pub fn new(params: Params) -> Self {
+        ClientKey {
+            params,
+            lwe_sk: lwe_keygen(&params),
+            glwe_sk: keygen(&params),
+        }
+    }
+
+    /// Returns the key of ciphertexts at rest, which depends on `params.pbs_order`:
+    /// the LWE key for [`PbsOrder::BootstrapKeyswitch`], the recoded GLWE key for [`PbsOrder::KeyswitchBootstrap`].
+    pub fn encryption_key(&self) -> LweSecretKey {
+        match self.params.pbs_order {
+            PbsOrder::BootstrapKeyswitch => self.lwe_sk.clone(),
+            PbsOrder::KeyswitchBootstrap => self.glwe_sk.recode(),
+        }
+    }
+
+    /// Encodes and encrypts `msg` under the key of ciphertexts at rest.
+    pub fn encrypt(&self, msg: u8) -> LweCiphertext {
+        let std_dev = match self.params.pbs_order {
+            PbsOrder::BootstrapKeyswitch => self.params.lwe_std_dev,
+            PbsOrder::KeyswitchBootstrap => self.params.glwe_std_dev,
+        };
+
+        LweCiphertext::encrypt(encode(msg, &self.params), &self.encryption_key(), std_dev)
+    }
+
+    /// Decrypts and decodes `ct`, which is assumed to be encrypted under the key of ciphertexts at rest.
+    pub fn decrypt(&self, ct: &LweCiphertext) -> u8 {
+        decode(ct.clone().decrypt(&self.encryption_key()), &self.params)
+    }
+
+    /// Generates the key used by [`crate::packing::pack_lwe_into_glwe`] to pack ciphertexts at rest.
+    pub fn packing_key(&self) -> PackingKeySwitchKey<N> {
+        compute_packing_ksk(&self.encryption_key(), &self.glwe_sk, &self.params)
+    }
+
+    /// Decrypts and decodes the first `count` messages of a packed ciphertext.
+    pub fn decrypt_packed(&self, ct: &GlweCiphertext<N>, count: usize) -> Vec<u8> {
+        let mut msgs = decode_poly(&ct.decrypt_poly(&self.glwe_sk), &self.params);
+        msgs.truncate(count);
+        msgs
+    }
+}
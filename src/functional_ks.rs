@@ -0,0 +1,211 @@
+use crate::glwe::{GlweCiphertext, SecretKey};
+use crate::lwe::{LweCiphertext, LweSecretKey};
+use crate::packing::{sub_scaled_assign, PackingKeySwitchKey};
+use crate::params::{DecompositionParams, Params};
+use crate::poly::ResiduePoly;
+use crate::utils::{decompose, gadget, MAX_LEVEL};
+use serde::{Deserialize, Serialize};
+
+/// Encryptions under a GLWE key of the gadget decomposition of a LWE key.
+/// This is the same key as the packing key, which switches with `f(x) = sum_i x_i X^i`.
+pub type PublicFunctionalKeySwitchKey<const N: usize> = PackingKeySwitchKey<N>;
+
+/// Encryptions under a GLWE key of `f(0, ..., K_i * g_j, ..., 0)` for every input position `z`,
+/// coefficient `K_i` of the extended LWE key `K = (s, -1)` and gadget entry `g_j`.
+/// The function `f` cannot be recovered without the GLWE key.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PrivateFunctionalKeySwitchKey<const N: usize> {
+    pub decomp: DecompositionParams,
+    /// Number of LWE ciphertexts taken as input.
+    pub input_count: usize,
+    /// Laid out as `z * (n + 1) * level + i * level + j`.
+    pub cts: Vec<GlweCiphertext<N>>,
+}
+
+/// Switches `cts` to a GLWE encryption of `f` applied to their messages.
+/// `f` is a public linear map from `cts.len()` torus elements to a polynomial,
+/// and must not blow up small inputs since it is evaluated on decomposition digits.
+pub fn public_functional_keyswitch<const N: usize, F: Fn(&[u64]) -> ResiduePoly<N>>(
+    cts: &[LweCiphertext],
+    ksk: &PublicFunctionalKeySwitchKey<N>,
+    f: F,
+) -> GlweCiphertext<N> {
+    let level = ksk.decomp.level;
+    let mut res = GlweCiphertext::zero(ksk.cts[0].mask.len());
+
+    let bodies: Vec<u64> = cts.iter().map(|ct| ct.body).collect();
+    res.body = f(&bodies);
+
+    // digits of the i-th mask coefficient of every input ciphertext
+    let mut digits = vec![[0u64; MAX_LEVEL]; cts.len()];
+    let mut level_digits = vec![0u64; cts.len()];
+
+    for (i, ksk_cts) in ksk.cts.chunks(level).enumerate() {
+        for (d, ct) in digits.iter_mut().zip(cts) {
+            decompose(ct.mask[i], &ksk.decomp, d);
+        }
+
+        for (j, ksk_ct) in ksk_cts.iter().enumerate() {
+            for (x, d) in level_digits.iter_mut().zip(&digits) {
+                *x = d[j];
+            }
+            let scale = -f(&level_digits);
+
+            for (x, y) in res.mask.iter_mut().zip(&ksk_ct.mask) {
+                x.add_mul_assign(&scale, y);
+            }
+            res.body.add_mul_assign(&scale, &ksk_ct.body);
+        }
+    }
+
+    res
+}
+
+/// Encrypts `f` applied to the gadget decomposition of `(s, -1)` under `sk`,
+/// for a private linear map `f` of `input_count` torus elements.
+pub fn compute_private_functional_ksk<const N: usize, F: Fn(&[u64]) -> ResiduePoly<N>>(
+    s: &LweSecretKey,
+    sk: &SecretKey<N>,
+    params: &Params,
+    input_count: usize,
+    f: F,
+) -> PrivateFunctionalKeySwitchKey<N> {
+    let mut cts = Vec::with_capacity(input_count * (s.len() + 1) * params.ks.level);
+    let mut input = vec![0u64; input_count];
+
+    for z in 0..input_count {
+        for coef in s.iter().chain([&1u64.wrapping_neg()]) {
+            for j in 0..params.ks.level {
+                input[z] = coef.wrapping_mul(gadget(j, &params.ks));
+                cts.push(GlweCiphertext::encrypt_poly(
+                    &f(&input),
+                    sk,
+                    params.glwe_std_dev,
+                ));
+            }
+        }
+        input[z] = 0;
+    }
+
+    PrivateFunctionalKeySwitchKey {
+        decomp: params.ks,
+        input_count,
+        cts,
+    }
+}
+
+/// Switches `cts` to a GLWE encryption of the private function of `ksk` applied to their messages.
+pub fn private_functional_keyswitch<const N: usize>(
+    cts: &[LweCiphertext],
+    ksk: &PrivateFunctionalKeySwitchKey<N>,
+) -> GlweCiphertext<N> {
+    assert_eq!(
+        cts.len(),
+        ksk.input_count,
+        "wrong number of input ciphertexts"
+    );
+    assert!(!cts.is_empty(), "no input ciphertexts");
+
+    let level = ksk.decomp.level;
+    let chunk_size = (cts[0].mask.len() + 1) * level;
+    assert_eq!(
+        ksk.cts.len(),
+        cts.len() * chunk_size,
+        "the key does not match the dimension of the input ciphertexts"
+    );
+
+    let mut res = GlweCiphertext::zero(ksk.cts[0].mask.len());
+
+    // phase(ct) = b - <a, s> = -<(a, b), (s, -1)>
+    let mut digits = [0u64; MAX_LEVEL];
+    for (ct, ksk_cts) in cts.iter().zip(ksk.cts.chunks(chunk_size)) {
+        for (a, ksk_cts) in ct.mask.iter().chain([&ct.body]).zip(ksk_cts.chunks(level)) {
+            decompose(*a, &ksk.decomp, &mut digits);
+            for (d, ksk_ct) in digits.into_iter().zip(ksk_cts) {
+                for (x, y) in res.mask.iter_mut().zip(&ksk_ct.mask) {
+                    sub_scaled_assign(x, y, d);
+                }
+                sub_scaled_assign(&mut res.body, &ksk_ct.body, d);
+            }
+        }
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::functional_ks::{
+        compute_private_functional_ksk, private_functional_keyswitch, public_functional_keyswitch,
+    };
+    use crate::glwe::keygen;
+    use crate::lwe::{lwe_keygen, LweCiphertext};
+    use crate::packing::compute_packing_ksk;
+    use crate::params::FAST_TEST_INSECURE;
+    use crate::poly::ResiduePoly;
+    use crate::utils::{decode, encode};
+    use rand::{thread_rng, Rng};
+
+    #[test]
+    fn test_public_functional_keyswitch() {
+        let params = FAST_TEST_INSECURE;
+        let lwe_sk = lwe_keygen(&params);
+        let glwe_sk = keygen::<256>(&params);
+        let ksk = compute_packing_ksk(&lwe_sk, &glwe_sk, &params);
+
+        // f(x, y) = (x - y) + 2x * X^3
+        let f = |x: &[u64]| {
+            let mut res = ResiduePoly::<256>::default();
+            res.coefs[0] = x[0].wrapping_sub(x[1]);
+            res.coefs[3] = x[0].wrapping_mul(2);
+            res
+        };
+
+        for _ in 0..10 {
+            let msgs: Vec<u8> = (0..2).map(|_| thread_rng().gen_range(0..16)).collect();
+            let cts: Vec<LweCiphertext> = msgs
+                .iter()
+                .map(|m| LweCiphertext::encrypt(encode(*m, &params), &lwe_sk, params.lwe_std_dev))
+                .collect();
+
+            let pt = public_functional_keyswitch(&cts, &ksk, f).decrypt_poly(&glwe_sk);
+            assert_eq!(
+                decode(pt.coefs[0], &params),
+                msgs[0].wrapping_sub(msgs[1]) % 16
+            );
+            assert_eq!(decode(pt.coefs[3], &params), msgs[0] * 2 % 16);
+            assert_eq!(decode(pt.coefs[1], &params), 0);
+        }
+    }
+
+    #[test]
+    fn test_private_functional_keyswitch() {
+        let params = FAST_TEST_INSECURE;
+        let lwe_sk = lwe_keygen(&params);
+        let glwe_sk = keygen::<256>(&params);
+
+        // f(x, y) = x * (1 - X) + y * X^5, with coefficients hidden in the key
+        let f = |x: &[u64]| {
+            let mut res = ResiduePoly::<256>::default();
+            res.coefs[0] = x[0];
+            res.coefs[1] = x[0].wrapping_neg();
+            res.coefs[5] = x[1];
+            res
+        };
+        let ksk = compute_private_functional_ksk(&lwe_sk, &glwe_sk, &params, 2, f);
+
+        for _ in 0..10 {
+            let msgs: Vec<u8> = (0..2).map(|_| thread_rng().gen_range(0..16)).collect();
+            let cts: Vec<LweCiphertext> = msgs
+                .iter()
+                .map(|m| LweCiphertext::encrypt(encode(*m, &params), &lwe_sk, params.lwe_std_dev))
+                .collect();
+
+            let pt = private_functional_keyswitch(&cts, &ksk).decrypt_poly(&glwe_sk);
+            assert_eq!(decode(pt.coefs[0], &params), msgs[0]);
+            assert_eq!(decode(pt.coefs[1], &params), (16 - msgs[0]) % 16);
+            assert_eq!(decode(pt.coefs[5], &params), msgs[1]);
+            assert_eq!(decode(pt.coefs[2], &params), 0);
+        }
+    }
+}
@@ -1,125 +1,288 @@
-use crate::utils::round_value;
-use crate::LWE_DIM;
-use crate::{glwe::GlweCiphertext, k, poly::ResiduePoly, ELL, N};
-use crate::{glwe::SecretKey, lwe::LweSecretKey};
+use crate::glwe::{GlweCiphertext, SecretKey};
+use crate::lwe::LweSecretKey;
+use crate::params::{DecompositionParams, Params};
+use crate::poly::ResiduePoly;
+use crate::torus::UnsignedTorus;
+use crate::utils::{gadget, round_value};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 
-pub type BootstrappingKey = Vec<GgswCiphertext>;
+pub type BootstrappingKey<const N: usize> = Vec<GgswCiphertext<N>>;
 
-#[derive(Default, Clone, Serialize, Deserialize)]
-pub struct GgswCiphertext {
-    z_m_gt: Vec<GlweCiphertext>,
+/// Encryptions of `(s_i == 1, s_i == -1)` for every coefficient `s_i` of a ternary LWE key.
+pub type TernaryBootstrappingKey<const N: usize> = Vec<(GgswCiphertext<N>, GgswCiphertext<N>)>;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GgswCiphertext<const N: usize> {
+    pub(crate) z_m_gt: Vec<GlweCiphertext<N>>,
+    pub(crate) decomp: DecompositionParams,
 }
 
-impl GgswCiphertext {
-    pub fn encrypt(msg: u8, sk: &SecretKey) -> Self {
+impl<const N: usize> GgswCiphertext<N> {
+    /// Encrypts the constant `msg`, see [`GgswCiphertext::encrypt_poly`].
+    pub fn encrypt(msg: u8, sk: &SecretKey<N>, std_dev: f64, decomp: &DecompositionParams) -> Self {
+        let mut m = ResiduePoly::default();
+        m.coefs[0] = msg as u64;
+        Self::encrypt_poly(&m, sk, std_dev, decomp)
+    }
+
+    /// Encrypts the polynomial `msg`, whose coefficients should be small (e.g. a monomial `X^a`)
+    /// since they multiply the noise of the GLWE ciphertexts in external products.
+    pub fn encrypt_poly(
+        msg: &ResiduePoly<N>,
+        sk: &SecretKey<N>,
+        std_dev: f64,
+        decomp: &DecompositionParams,
+    ) -> Self {
+        let k = sk.polys.len();
+
         // initialize Z
-        let mut z_m_gt: Vec<GlweCiphertext> = (0..(k + 1) * ELL)
-            .map(|_| GlweCiphertext::encrypt(0, sk))
+        let mut z_m_gt: Vec<GlweCiphertext<N>> = (0..(k + 1) * decomp.level)
+            .map(|_| GlweCiphertext::encrypt(0, sk, std_dev))
             .collect();
 
         // m * g, g being [q/B, ..., q/B^l]
-        let mut mg = [0u64; ELL];
-        mg[0] = (msg as u64) << 56;
-        mg[1] = (msg as u64) << 48;
+        let mg: Vec<ResiduePoly<N>> = (0..decomp.level)
+            .map(|j| msg * gadget::<u64>(j, decomp))
+            .collect();
 
         // add m * G^t to Z
-        for i in 0..z_m_gt.len() {
-            if i < k * ELL {
-                for j in 0..z_m_gt[i].mask.len() {
-                    z_m_gt[i].mask[j].add_constant_assign(mg[i % ELL]);
-                }
+        for (i, ct) in z_m_gt.iter_mut().enumerate() {
+            let m = &mg[i % decomp.level];
+            if i < k * decomp.level {
+                ct.mask[i / decomp.level] += m;
             } else {
-                z_m_gt[i].body.add_constant_assign(mg[i % ELL]);
+                ct.body += m;
             }
         }
 
-        GgswCiphertext { z_m_gt }
+        GgswCiphertext {
+            z_m_gt,
+            decomp: *decomp,
+        }
     }
 
-    // The last `GlweCiphertext` of `z_m_gt` is an encryption of msg * q/B^l
-    pub fn decrypt(self, sk: &SecretKey) -> u8 {
-        ((((&self.z_m_gt[self.z_m_gt.len() - 1].decrypt(sk) >> 47) + 1) >> 1) % 16) as u8
+    /// Decrypts the constant coefficient of the message as a signed integer, see [`GgswCiphertext::decrypt_poly`].
+    pub fn decrypt(self, sk: &SecretKey<N>) -> i64 {
+        self.decrypt_poly(sk).coefs[0] as i64
     }
 
-    /// Performs a product (GGSW x GLWE) -> GLWE.
-    pub fn external_product(&self, ct: &GlweCiphertext) -> GlweCiphertext {
-        let g_inverse_ct = apply_g_inverse(ct);
+    /// Decrypts the message, whose coefficients are returned as signed integers in `[-B^l/2, B^l/2)`.
+    // The last row of the body part of `z_m_gt` is an encryption of msg * q/B^l
+    pub fn decrypt_poly(&self, sk: &SecretKey<N>) -> ResiduePoly<N> {
+        let bits = self.decomp.base_log * self.decomp.level;
+        let modulus = 1u64 << bits;
+        let phase = self.z_m_gt[self.z_m_gt.len() - 1].decrypt_poly(sk);
 
-        let mut res = GlweCiphertext::default();
-        for i in 0..(k + 1) * ELL {
-            for j in 0..k {
-                res.mask[j].add_assign(&g_inverse_ct[i].mul(&self.z_m_gt[i].mask[j]));
-            }
-            res.body
-                .add_assign(&g_inverse_ct[i].mul(&self.z_m_gt[i].body));
+        ResiduePoly {
+            coefs: phase.coefs.map(|c| {
+                let m = round_value(c, bits) % modulus;
+                if m >= modulus / 2 {
+                    m.wrapping_sub(modulus)
+                } else {
+                    m
+                }
+            }),
         }
+    }
+
+    /// Performs a product (GGSW x GLWE) -> GLWE.
+    pub fn external_product(&self, ct: &GlweCiphertext<N>) -> GlweCiphertext<N> {
+        let mut res = GlweCiphertext::zero(ct.mask.len());
+        self.add_external_product_assign(&mut res, ct, &mut self.g_inverse_buffer());
         res
     }
-}
 
-/// Decomposition of a GLWE ciphertext.
-fn apply_g_inverse(ct: &GlweCiphertext) -> Vec<ResiduePoly> {
-    let mut res: [ResiduePoly; (k + 1) * ELL] = Default::default();
+    /// Performs a product (GGSW x GGSW) -> GGSW, i.e. the external product of `self` with every row of `rhs`.
+    /// The result encrypts the product of the messages with the decomposition of `rhs`.
+    /// Its noise is the one of `rhs` scaled by the message of `self`, plus the external product noise.
+    pub fn internal_product(&self, rhs: &GgswCiphertext<N>) -> GgswCiphertext<N> {
+        let mut g_inverse_buffer = self.g_inverse_buffer();
+        let z_m_gt = rhs
+            .z_m_gt
+            .iter()
+            .map(|row| {
+                let mut res = GlweCiphertext::zero(row.mask.len());
+                self.add_external_product_assign(&mut res, row, &mut g_inverse_buffer);
+                res
+            })
+            .collect();
 
-    for i in 0..N {
-        // mask decomposition
-        for j in 0..k {
-            let (nu_2, nu_1) = decomposition_8_2(ct.mask[j].coefs[i]);
-            res[j * ELL].coefs[i] = nu_1 as u64;
-            res[j * ELL + 1].coefs[i] = nu_2 as u64;
+        GgswCiphertext {
+            z_m_gt,
+            decomp: rhs.decomp,
         }
+    }
 
-        // body decomposition
-        let (nu_2, nu_1) = decomposition_8_2(ct.body.coefs[i]);
-        res[(k + 1) * ELL - 2].coefs[i] = nu_1 as u64;
-        res[(k + 1) * ELL - 1].coefs[i] = nu_2 as u64;
+    /// Returns scratch space for [`GgswCiphertext::add_external_product_assign`].
+    pub fn g_inverse_buffer(&self) -> Vec<ResiduePoly<N>> {
+        vec![ResiduePoly::default(); self.z_m_gt.len()]
+    }
+
+    /// Adds the external product of `self` and `ct` to `out`.
+    /// `g_inverse_buffer` holds the decomposition of `ct`, it is reused across calls to avoid allocations.
+    #[cfg(not(feature = "parallel"))]
+    pub fn add_external_product_assign(
+        &self,
+        out: &mut GlweCiphertext<N>,
+        ct: &GlweCiphertext<N>,
+        g_inverse_buffer: &mut [ResiduePoly<N>],
+    ) {
+        apply_g_inverse(ct, &self.decomp, g_inverse_buffer);
+
+        for (d, z) in g_inverse_buffer.iter().zip(&self.z_m_gt) {
+            for (a, z_a) in out.mask.iter_mut().zip(&z.mask) {
+                a.add_mul_assign(d, z_a);
+            }
+            out.body.add_mul_assign(d, &z.body);
+        }
+    }
+
+    /// Adds the external product of `self` and `ct` to `out`.
+    /// `g_inverse_buffer` holds the decomposition of `ct`, it is reused across calls to avoid allocations.
+    /// The rows of `self` (one per decomposition level) and their polynomial products are processed in parallel.
+    #[cfg(feature = "parallel")]
+    pub fn add_external_product_assign(
+        &self,
+        out: &mut GlweCiphertext<N>,
+        ct: &GlweCiphertext<N>,
+        g_inverse_buffer: &mut [ResiduePoly<N>],
+    ) {
+        apply_g_inverse(ct, &self.decomp, g_inverse_buffer);
+
+        let product = g_inverse_buffer
+            .par_iter()
+            .zip(self.z_m_gt.par_iter())
+            .map(|(d, z)| {
+                let (mask, body) = rayon::join(
+                    || z.mask.par_iter().map(|z_a| d * z_a).collect(),
+                    || d * &z.body,
+                );
+                GlweCiphertext { mask, body }
+            })
+            .reduce_with(|acc, row| acc + row)
+            .unwrap();
+
+        *out += product;
     }
-    res.to_vec()
 }
 
-/// Approximate decomposition with lg(B) = 8 and ell = 2.
-/// Takes a polynomial coefficient in Z_{2^64} and decomposes its 16 MSBs in two signed 8-bit integers.
-pub fn decomposition_8_2(val: u64) -> (i8, i8) {
-    let rounded_val = round_value(val);
-    if rounded_val & 128 == 128 {
-        (rounded_val as i8, ((rounded_val >> 8) + 1) as i8)
-    } else {
-        (rounded_val as i8, (rounded_val >> 8) as i8)
+/// Decomposition of a GLWE ciphertext, written to `res`.
+fn apply_g_inverse<const N: usize>(
+    ct: &GlweCiphertext<N>,
+    decomp: &DecompositionParams,
+    res: &mut [ResiduePoly<N>],
+) {
+    for (poly, decomposed) in ct
+        .mask
+        .iter()
+        .chain([&ct.body])
+        .zip(res.chunks_mut(decomp.level))
+    {
+        decompose_poly(poly, decomp, decomposed);
     }
 }
 
+/// Decomposition of a polynomial in `level` polynomials with small coefficients, written to `res`.
+pub(crate) fn decompose_poly<const N: usize, T: UnsignedTorus>(
+    poly: &ResiduePoly<N, T>,
+    decomp: &DecompositionParams,
+    res: &mut [ResiduePoly<N, T>],
+) {
+    T::decompose_poly(poly, decomp, res);
+}
+
 /// Ciphertext multiplexer. If `ctb` is an encryption of `0`, return `ct1`. Else, return `ct2`.
 /// `ctb` is assumed to be an encryption of `0` or `1`.
-pub fn cmux(ctb: &GgswCiphertext, ct1: &GlweCiphertext, ct2: &GlweCiphertext) -> GlweCiphertext {
-    let mut res = ct2.sub(ct1);
-    res = ctb.external_product(&res);
-    res = res.add(ct1);
+pub fn cmux<const N: usize>(
+    ctb: &GgswCiphertext<N>,
+    ct1: &GlweCiphertext<N>,
+    ct2: &GlweCiphertext<N>,
+) -> GlweCiphertext<N> {
+    let mut res = ct1.clone();
+    ctb.add_external_product_assign(&mut res, &(ct2 - ct1), &mut ctb.g_inverse_buffer());
     res
 }
 
+/// Selects `cts[i]` where `i` is the index encrypted bit by bit in `selectors`, least significant bit first.
+/// `cts` holds `2^selectors.len()` ciphertexts.
+pub fn cmux_tree<const N: usize>(
+    selectors: &[GgswCiphertext<N>],
+    cts: &[GlweCiphertext<N>],
+) -> GlweCiphertext<N> {
+    assert_eq!(
+        cts.len(),
+        1 << selectors.len(),
+        "wrong number of ciphertexts"
+    );
+
+    let mut level = cts.to_vec();
+    for ctb in selectors {
+        level = level
+            .chunks(2)
+            .map(|pair| cmux(ctb, &pair[0], &pair[1]))
+            .collect();
+    }
+
+    level.pop().unwrap()
+}
+
 /// Encrypts the bits of `s` under `sk`
-pub fn compute_bsk(s: &LweSecretKey, sk: &SecretKey) -> BootstrappingKey {
-    let bsk: Vec<GgswCiphertext> = (0..LWE_DIM)
-        .map(|i| GgswCiphertext::encrypt(s[i].try_into().unwrap(), sk))
+pub fn compute_bsk<const N: usize>(
+    s: &LweSecretKey,
+    sk: &SecretKey<N>,
+    params: &Params,
+) -> BootstrappingKey<N> {
+    let bsk: Vec<GgswCiphertext<N>> = s
+        .iter()
+        .map(|bit| {
+            assert!(*bit <= 1, "the LWE key should be binary");
+            GgswCiphertext::encrypt(*bit as u8, sk, params.glwe_std_dev, &params.pbs)
+        })
         .collect();
 
     bsk
 }
 
+/// Encrypts the positive and negative parts of the ternary key `s` under `sk`
+pub fn compute_ternary_bsk<const N: usize>(
+    s: &LweSecretKey,
+    sk: &SecretKey<N>,
+    params: &Params,
+) -> TernaryBootstrappingKey<N> {
+    s.iter()
+        .map(|coef| {
+            let (plus, minus) = match *coef as i64 {
+                0 => (0, 0),
+                1 => (1, 0),
+                -1 => (0, 1),
+                _ => panic!("the LWE key should be ternary"),
+            };
+            (
+                GgswCiphertext::encrypt(plus, sk, params.glwe_std_dev, &params.pbs),
+                GgswCiphertext::encrypt(minus, sk, params.glwe_std_dev, &params.pbs),
+            )
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::ggsw::{cmux, GgswCiphertext};
+    use crate::ggsw::{cmux, cmux_tree, GgswCiphertext};
     use crate::glwe::{keygen, GlweCiphertext};
-    use crate::utils::{decode, encode};
+    use crate::params::{DecompositionParams, Params, FAST_TEST_INSECURE, MESSAGE_3};
+    use crate::poly::ResiduePoly;
+    use crate::utils::{decode, decode_poly, encode, encode_poly};
     use rand::{thread_rng, Rng};
 
     #[test]
     fn test_keygen_enc_dec() {
-        let sk = keygen();
+        let params = MESSAGE_3;
+        let sk = keygen::<1024>(&params);
         for _ in 0..100 {
             let msg = thread_rng().gen_range(0..16);
-            let ct = GgswCiphertext::encrypt(msg, &sk);
+            let ct = GgswCiphertext::encrypt(msg, &sk, params.glwe_std_dev, &params.pbs);
             let pt = ct.decrypt(&sk);
             assert_eq!(msg, pt as u8);
         }
@@ -127,14 +290,15 @@ mod tests {
 
     #[test]
     fn test_external_product() {
-        let sk = keygen();
+        let params = MESSAGE_3;
+        let sk = keygen::<1024>(&params);
         for _ in 0..100 {
             let msg1 = thread_rng().gen_range(0..16);
             let msg2 = thread_rng().gen_range(0..16);
-            let ct1 = GgswCiphertext::encrypt(msg1, &sk);
-            let ct2 = GlweCiphertext::encrypt(encode(msg2), &sk);
+            let ct1 = GgswCiphertext::encrypt(msg1, &sk, params.glwe_std_dev, &params.pbs);
+            let ct2 = GlweCiphertext::encrypt(encode(msg2, &params), &sk, params.glwe_std_dev);
             let res = ct1.external_product(&ct2);
-            let pt = decode(res.decrypt(&sk));
+            let pt = decode(res.decrypt(&sk), &params);
             let expected: u8 = msg1 * msg2 % 16;
             assert_eq!(expected, pt);
         }
@@ -143,18 +307,19 @@ mod tests {
     #[test]
     fn test_cmux() {
         for _ in 0..100 {
-            let sk = keygen();
+            let params = MESSAGE_3;
+            let sk = keygen::<1024>(&params);
             let msg1 = thread_rng().gen_range(0..16);
             let msg2 = thread_rng().gen_range(0..16);
             let b = thread_rng().gen_range(0..2);
 
-            let ct1 = GlweCiphertext::encrypt(encode(msg1), &sk);
-            let ct2 = GlweCiphertext::encrypt(encode(msg2), &sk);
-            let ctb = GgswCiphertext::encrypt(b, &sk);
+            let ct1 = GlweCiphertext::encrypt(encode(msg1, &params), &sk, params.glwe_std_dev);
+            let ct2 = GlweCiphertext::encrypt(encode(msg2, &params), &sk, params.glwe_std_dev);
+            let ctb = GgswCiphertext::encrypt(b, &sk, params.glwe_std_dev, &params.pbs);
 
             let res = cmux(&ctb, &ct1, &ct2);
 
-            let pt = decode(res.decrypt(&sk));
+            let pt = decode(res.decrypt(&sk), &params);
             assert_eq!(pt, (1 - b) * msg1 + b * msg2);
         }
     }
@@ -162,20 +327,132 @@ mod tests {
     #[test]
     fn test_cmux_trivial() {
         for _ in 0..100 {
-            let sk = keygen();
+            let params = MESSAGE_3;
+            let sk = keygen::<1024>(&params);
             let msg1 = thread_rng().gen_range(0..16);
             let msg2 = thread_rng().gen_range(0..16);
             let b = thread_rng().gen_range(0..2);
 
-            let ct1 = GlweCiphertext::trivial_encrypt(encode(msg1));
-            let ct2 = GlweCiphertext::trivial_encrypt(encode(msg2));
-            let ctb = GgswCiphertext::encrypt(b, &sk);
+            let ct1 = GlweCiphertext::trivial_encrypt(encode(msg1, &params), &params);
+            let ct2 = GlweCiphertext::trivial_encrypt(encode(msg2, &params), &params);
+            let ctb = GgswCiphertext::encrypt(b, &sk, params.glwe_std_dev, &params.pbs);
 
             let res = cmux(&ctb, &ct1, &ct2);
 
-            let pt = decode(res.decrypt(&sk));
+            let pt = decode(res.decrypt(&sk), &params);
 
             assert_eq!(pt, (1 - b) * msg1 + b * msg2);
         }
     }
+
+    #[test]
+    fn test_cmux_tree() {
+        let params = MESSAGE_3;
+        let sk = keygen::<1024>(&params);
+        let cts: Vec<GlweCiphertext<1024>> = (0..8)
+            .map(|msg| GlweCiphertext::encrypt(encode(msg, &params), &sk, params.glwe_std_dev))
+            .collect();
+
+        for index in 0..8 {
+            let selectors: Vec<GgswCiphertext<1024>> = (0..3)
+                .map(|i| {
+                    GgswCiphertext::encrypt((index >> i) & 1, &sk, params.glwe_std_dev, &params.pbs)
+                })
+                .collect();
+
+            let res = cmux_tree(&selectors, &cts);
+            assert_eq!(decode(res.decrypt(&sk), &params), index);
+        }
+    }
+
+    #[test]
+    fn test_internal_product() {
+        // the noise of the product is amplified by the next external product, which needs a precise decomposition
+        let params = Params {
+            pbs: DecompositionParams {
+                base_log: 6,
+                level: 6,
+            },
+            ..FAST_TEST_INSECURE
+        };
+        let sk = keygen::<256>(&params);
+        let ct1 = GlweCiphertext::encrypt(encode(3, &params), &sk, params.glwe_std_dev);
+        let ct2 = GlweCiphertext::encrypt(encode(5, &params), &sk, params.glwe_std_dev);
+
+        for (b1, b2) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+            let ggsw1 = GgswCiphertext::encrypt(b1, &sk, params.glwe_std_dev, &params.pbs);
+            let ggsw2 = GgswCiphertext::encrypt(b2, &sk, params.glwe_std_dev, &params.pbs);
+
+            let and = ggsw1.internal_product(&ggsw2);
+            let res = cmux(&and, &ct1, &ct2);
+
+            let pt = decode(res.decrypt(&sk), &params);
+            assert_eq!(pt, if b1 & b2 == 1 { 5 } else { 3 });
+        }
+    }
+
+    #[test]
+    fn test_poly_enc_dec() {
+        let params = FAST_TEST_INSECURE;
+        let sk = keygen::<256>(&params);
+        for decomp in [
+            params.pbs,
+            DecompositionParams {
+                base_log: 3,
+                level: 5,
+            },
+        ] {
+            let mut msg = ResiduePoly::<256>::get_random_ternary();
+            msg.coefs[0] = 3;
+            let ct = GgswCiphertext::encrypt_poly(&msg, &sk, params.glwe_std_dev, &decomp);
+            assert_eq!(ct.decrypt_poly(&sk).coefs, msg.coefs);
+            assert_eq!(ct.decrypt(&sk), 3);
+        }
+    }
+
+    #[test]
+    fn test_dec_small_base() {
+        let params = FAST_TEST_INSECURE;
+        let sk = keygen::<256>(&params);
+        for (base_log, level) in [(1, 8), (2, 6)] {
+            let decomp = DecompositionParams { base_log, level };
+            // messages at least B/2 do not fit in a single digit
+            for msg in [1, 2, 3, 7, 15] {
+                let ct = GgswCiphertext::encrypt(msg, &sk, params.glwe_std_dev, &decomp);
+                assert_eq!(ct.decrypt(&sk), msg as i64);
+            }
+
+            let mut msg = ResiduePoly::<256>::get_random_ternary();
+            msg.coefs[1] = 5;
+            msg.coefs[2] = (-4i64) as u64;
+            let ct = GgswCiphertext::encrypt_poly(&msg, &sk, params.glwe_std_dev, &decomp);
+            assert_eq!(ct.decrypt_poly(&sk).coefs, msg.coefs);
+        }
+    }
+
+    #[test]
+    fn test_monomial_external_product() {
+        let params = FAST_TEST_INSECURE;
+        let sk = keygen::<256>(&params);
+        let msgs: Vec<u8> = (0..256).map(|_| thread_rng().gen_range(0..16)).collect();
+        let ct =
+            GlweCiphertext::encrypt_poly(&encode_poly(&msgs, &params), &sk, params.glwe_std_dev);
+
+        for _ in 0..10 {
+            let a = thread_rng().gen_range(0..512);
+            let mut monomial = ResiduePoly::<256>::default();
+            monomial.coefs[0] = 1;
+            monomial.multiply_by_monomial_assign(a);
+
+            let ggsw =
+                GgswCiphertext::encrypt_poly(&monomial, &sk, params.glwe_std_dev, &params.pbs);
+            let res = ggsw.external_product(&ct);
+
+            let expected = ct.rotate(a as u64).decrypt_poly(&sk);
+            assert_eq!(
+                decode_poly(&res.decrypt_poly(&sk), &params),
+                decode_poly(&expected, &params)
+            );
+        }
+    }
 }
@@ -1,169 +1,449 @@
-use crate::ggsw::{cmux, BootstrappingKey};
-use crate::lwe::{LweCiphertext, LweSecretKey};
-use crate::utils::encode;
-use crate::P;
-use crate::{k, poly::ResiduePoly, LWE_DIM, N};
+use crate::ggsw::{decompose_poly, BootstrappingKey, TernaryBootstrappingKey};
+use crate::lwe::{LweCiphertext, LweSecretKey, ModSwitchedLweCiphertext};
+use crate::params::{DecompositionParams, Params, SecretKeyDistribution};
+use crate::poly::ResiduePoly;
+use crate::torus::UnsignedTorus;
+use crate::utils::{encode, gadget, round_value};
 use rand_distr::{Distribution, Normal};
 use serde::{Deserialize, Serialize};
+use std::ops::{AddAssign, MulAssign, Neg, SubAssign};
 
 #[derive(Clone, Serialize, Deserialize)]
-pub struct GlweCiphertext {
-    pub mask: Vec<ResiduePoly>,
-    pub body: ResiduePoly,
+#[serde(bound = "")]
+pub struct GlweCiphertext<const N: usize, T: UnsignedTorus = u64> {
+    pub mask: Vec<ResiduePoly<N, T>>,
+    pub body: ResiduePoly<N, T>,
 }
 
-/// Set of `k` polynomials in {0, 1}\[X\]/(X^N + 1).
+/// Encryptions of the gadget decomposition of an input GLWE key under an output GLWE key.
+/// The two keys share the same `N` but can have different dimensions.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct GlweKeySwitchKey<const N: usize, T: UnsignedTorus = u64> {
+    pub decomp: DecompositionParams,
+    /// Laid out as `i * level + j` for the `i`-th polynomial of the input key.
+    pub cts: Vec<GlweCiphertext<N, T>>,
+}
+
+/// Set of `k` polynomials in Z\[X\]/(X^N + 1) with small coefficients (binary, ternary or Gaussian).
 #[derive(Clone)]
-pub struct SecretKey {
-    pub polys: Vec<ResiduePoly>,
+pub struct SecretKey<const N: usize> {
+    pub polys: Vec<ResiduePoly<N>>,
 }
 
-impl GlweCiphertext {
-    pub fn encrypt(mu: u64, sk: &SecretKey) -> GlweCiphertext {
-        let sigma = f64::powf(2.0, 39.0);
+impl<const N: usize, T: UnsignedTorus> GlweCiphertext<N, T> {
+    /// Encrypts `mu` under `sk` with a noise of standard deviation `std_dev` (on the torus).
+    /// `mu` is the constant coefficient of the plaintext, see [`GlweCiphertext::encrypt_poly`].
+    pub fn encrypt(mu: T, sk: &SecretKey<N>, std_dev: f64) -> Self {
+        let mut plaintext = ResiduePoly::default();
+        plaintext.coefs[0] = mu;
+        Self::encrypt_poly(&plaintext, sk, std_dev)
+    }
+
+    /// Encrypts the polynomial `mu` under `sk`.
+    /// Every coefficient gets an independent noise of standard deviation `std_dev` (on the torus).
+    pub fn encrypt_poly(mu: &ResiduePoly<N, T>, sk: &SecretKey<N>, std_dev: f64) -> Self {
+        let sigma = std_dev * T::modulus_f64();
         let normal = Normal::new(0.0, sigma).unwrap();
 
-        let e = normal.sample(&mut rand::thread_rng()).round() as i64;
-        let mu_star = mu.wrapping_add_signed(e);
+        let mask: Vec<ResiduePoly<N, T>> = (0..sk.polys.len())
+            .map(|_| ResiduePoly::get_random())
+            .collect();
+
+        let mut body = ResiduePoly {
+            coefs: std::array::from_fn(|i| {
+                let e = normal.sample(&mut rand::thread_rng()).round() as i64;
+                mu.coefs[i].wrapping_add(T::from_i64(e))
+            }),
+        };
+        for (a, s) in mask.iter().zip(&sk.polys) {
+            body.add_mul_assign(a, &s.cast());
+        }
+
+        GlweCiphertext { mask, body }
+    }
 
-        let mask: Vec<ResiduePoly> = (0..k).map(|_| ResiduePoly::get_random()).collect();
+    /// Returns the constant coefficient of the phase of `self`.
+    pub fn decrypt(&self, sk: &SecretKey<N>) -> T {
+        self.decrypt_poly(sk).coefs[0]
+    }
 
+    /// Returns the phase of `self`, i.e. the noisy plaintext polynomial.
+    pub fn decrypt_poly(&self, sk: &SecretKey<N>) -> ResiduePoly<N, T> {
         let mut body = ResiduePoly::default();
-        for i in 0..k {
-            body.add_assign(&mask[i].mul(&sk.polys[i]));
+        for (a, s) in self.mask.iter().zip(&sk.polys) {
+            body.add_mul_assign(a, &s.cast());
         }
 
-        body.add_constant_assign(mu_star as u64);
+        &self.body - &body // mu_star
+    }
 
-        GlweCiphertext { mask, body }
+    /// Multiplies the encrypted message by the plaintext polynomial `p`.
+    /// The noise grows with the norm of `p`, whose coefficients should be small.
+    pub fn mul_plain(&self, p: &ResiduePoly<N, T>) -> Self {
+        self * p
     }
 
-    pub fn decrypt(&self, sk: &SecretKey) -> u64 {
-        let mut body = ResiduePoly::default();
-        for i in 0..k {
-            body.add_assign(&self.mask[i].mul(&sk.polys[i]));
-        }
+    /// In-place version of [`GlweCiphertext::mul_plain`].
+    pub fn mul_plain_assign(&mut self, p: &ResiduePoly<N, T>) {
+        *self *= p;
+    }
 
-        let mu_star = self.body.sub(&body);
-        mu_star.coefs[0]
+    /// Multiplies the encrypted message by the signed integer `constant`.
+    /// The noise is multiplied by `|constant|`.
+    pub fn multiply_constant(&self, constant: i64) -> Self {
+        self * T::from_i64(constant)
     }
 
-    pub fn add(&self, rhs: &Self) -> Self {
-        let mut res = GlweCiphertext::default();
-        for i in 0..k {
-            res.mask[i] = self.mask[i].add(&rhs.mask[i]);
+    /// In-place version of [`GlweCiphertext::multiply_constant`].
+    pub fn multiply_constant_assign(&mut self, constant: i64) -> &mut Self {
+        *self *= T::from_i64(constant);
+        self
+    }
+
+    /// Converts a GLWE ciphertext into a LWE ciphertext of dimension `kN`
+    /// encrypting the constant coefficient of the plaintext.
+    pub fn sample_extract(&self) -> LweCiphertext<T> {
+        self.sample_extract_at(0)
+    }
+
+    /// Converts a GLWE ciphertext into a LWE ciphertext of dimension `kN`
+    /// encrypting the coefficient of degree `index` of the plaintext.
+    /// The resulting ciphertext is encrypted under [`SecretKey::recode`].
+    pub fn sample_extract_at(&self, index: usize) -> LweCiphertext<T> {
+        assert!(index < N, "coefficient index out of bounds");
+
+        // the coefficient of degree `index` of a * s is sum_j a_{index - j} s_j, with a_{-j} = -a_{N - j}
+        let mut mask = Vec::<T>::with_capacity(self.mask.len() * N);
+        for poly in &self.mask {
+            mask.extend(poly.coefs[..=index].iter().rev());
+            mask.extend(
+                poly.coefs[index + 1..]
+                    .iter()
+                    .rev()
+                    .map(|c| c.wrapping_neg()),
+            );
         }
-        res.body = self.body.add(&rhs.body);
-        res
+
+        let body = self.body.coefs[index];
+
+        LweCiphertext { mask, body }
+    }
+
+    /// Extracts a LWE encryption of every coefficient of the plaintext.
+    pub fn sample_extract_all(&self) -> Vec<LweCiphertext<T>> {
+        (0..N).map(|i| self.sample_extract_at(i)).collect()
     }
 
-    pub fn sub(&self, rhs: &Self) -> Self {
-        let mut res = GlweCiphertext::default();
-        for i in 0..k {
-            res.mask[i] = self.mask[i].sub(&rhs.mask[i]);
+    /// Switches to the key encrypted by `ksk`.
+    pub fn keyswitch(&self, ksk: &GlweKeySwitchKey<N, T>) -> Self {
+        let level = ksk.decomp.level;
+        let mut keyswitched = GlweCiphertext {
+            body: self.body.clone(),
+            ..Self::zero(ksk.cts[0].mask.len())
+        };
+
+        let mut decomposed = vec![ResiduePoly::default(); level];
+        for (a, cts) in self.mask.iter().zip(ksk.cts.chunks(level)) {
+            decompose_poly(a, &ksk.decomp, &mut decomposed);
+            for (d, ct) in decomposed.iter_mut().zip(cts) {
+                *d = -&*d;
+                for (x, y) in keyswitched.mask.iter_mut().zip(&ct.mask) {
+                    x.add_mul_assign(d, y);
+                }
+                keyswitched.body.add_mul_assign(d, &ct.body);
+            }
         }
-        res.body = self.body.sub(&rhs.body);
-        res
+
+        keyswitched
     }
 
-    /// Converts a GLWE ciphertext into a LWE ciphertext of dimension `N`.
-    // TODO: generalize for k > 1
-    pub fn sample_extract(&self) -> LweCiphertext {
-        let mut mask = [0u64; N];
-        mask[0] = self.mask[0].coefs[0];
-        for i in 1..N {
-            mask[i] = self.mask[0].coefs[N - i].wrapping_neg();
+    /// Switches from ciphertext modulus `q` to `2^log_modulus`.
+    /// The coefficients of the result are in `[0, 2^log_modulus)`, which makes it more compact to transmit.
+    pub fn modswitch(&self, log_modulus: usize) -> Self {
+        let switch = |poly: &ResiduePoly<N, T>| ResiduePoly {
+            coefs: poly
+                .coefs
+                .map(|c| T::from_u64(round_value(c, log_modulus) % (1 << log_modulus))),
+        };
+
+        GlweCiphertext {
+            mask: self.mask.iter().map(switch).collect(),
+            body: switch(&self.body),
         }
+    }
 
-        let body = self.body.coefs[0];
+    /// Returns the phase of a ciphertext output by [`GlweCiphertext::modswitch`], modulo `2^log_modulus`.
+    pub fn decrypt_poly_modswitched(
+        &self,
+        sk: &SecretKey<N>,
+        log_modulus: usize,
+    ) -> ResiduePoly<N, T> {
+        // the phase is computed modulo 2^64, a multiple of 2^log_modulus unlike a prime q
+        let lift = |poly: &ResiduePoly<N, T>| ResiduePoly::<N> {
+            coefs: poly.coefs.map(|c| c.to_u64()),
+        };
+        let lifted = GlweCiphertext {
+            mask: self.mask.iter().map(lift).collect(),
+            body: lift(&self.body),
+        };
+
+        ResiduePoly {
+            coefs: lifted
+                .decrypt_poly(sk)
+                .coefs
+                .map(|c| T::from_u64(c % (1 << log_modulus))),
+        }
+    }
 
-        LweCiphertext {
-            mask: mask.to_vec(),
-            body,
+    /// Returns the trivial encryption of zero with `glwe_dim` mask polynomials.
+    pub fn zero(glwe_dim: usize) -> Self {
+        GlweCiphertext {
+            mask: vec![ResiduePoly::default(); glwe_dim],
+            body: ResiduePoly::default(),
         }
     }
 
     /// Trivially encrypts `mu`.
-    pub fn trivial_encrypt(mu: u64) -> Self {
-        let mut res = Self::default();
+    pub fn trivial_encrypt(mu: T, params: &Params) -> Self {
+        let mut res = Self::zero(params.glwe_dim);
         res.body.coefs[0] = mu;
         res
     }
 
-    /// Performs the blind rotation of `self`.
+    /// Multiplies by the monomial `X^exponent` the body of `self`.
+    /// `self` is assumed to be a trivial encryption.
+    fn rotate_trivial(&mut self, exponent: u64) {
+        self.body.multiply_by_monomial_assign(exponent as usize);
+    }
+
+    /// Multiplies by the monomial `X^exponent` every component of `self`.
+    pub fn rotate(&self, exponent: u64) -> Self {
+        let mut res = self.clone();
+        res.rotate_assign(exponent);
+        res
+    }
+
+    /// In-place version of [`GlweCiphertext::rotate`].
+    pub fn rotate_assign(&mut self, exponent: u64) {
+        for a in self.mask.iter_mut() {
+            a.multiply_by_monomial_assign(exponent as usize);
+        }
+        self.body.multiply_by_monomial_assign(exponent as usize);
+    }
+}
+
+impl<const N: usize> GlweCiphertext<N> {
+    /// Performs the blind rotation of `self` by the phase of `c`, which must be switched to modulus `2N`.
     // `self` is assumed to be a trivial encryption
-    // `c` is a modswitched LWE ciphertext (modulus = 2N)
-    pub fn blind_rotate(&self, c: LweCiphertext, bsk: &BootstrappingKey) -> Self {
+    pub fn blind_rotate(&self, c: ModSwitchedLweCiphertext, bsk: &BootstrappingKey<N>) -> Self {
         let mut c_prime = self.clone();
 
-        c_prime.rotate_trivial((2 * N as u64) - c.body);
-        for i in 0..LWE_DIM {
-            c_prime = cmux(&bsk[i], &c_prime, &c_prime.rotate(c.mask[i]));
+        let two_n = 2 * N as u64;
+        assert_eq!(
+            c.modulus(),
+            two_n,
+            "the ciphertext must be switched to modulus 2N"
+        );
+        c_prime.rotate_trivial((two_n - c.body) % two_n);
+
+        // scratch space reused by every CMux
+        let mut rotated = c_prime.clone();
+        let mut g_inverse_buffer = bsk[0].g_inverse_buffer();
+
+        for (a, ggsw) in c.mask.iter().zip(bsk) {
+            // CMux in place: c_prime += GGSW(s_i) * (X^a * c_prime - c_prime)
+            rotated.clone_from(&c_prime);
+            rotated.rotate_assign(*a);
+            rotated -= &c_prime;
+            ggsw.add_external_product_assign(&mut c_prime, &rotated, &mut g_inverse_buffer);
         }
 
         c_prime
     }
 
-    /// Multiplies by the monomial `X^exponent` the body of `self`.
-    /// `self` is assumed to be a trivial encryption.
-    fn rotate_trivial(&mut self, exponent: u64) {
-        self.body = self.body.multiply_by_monomial(exponent as usize);
+    /// Performs the blind rotation of `self` under a ternary LWE key.
+    /// Each step computes `ACC + GGSW(s+) * (X^a * ACC - ACC) + GGSW(s-) * (X^{-a} * ACC - ACC)`.
+    // `self` is assumed to be a trivial encryption
+    pub fn ternary_blind_rotate(
+        &self,
+        c: ModSwitchedLweCiphertext,
+        bsk: &TernaryBootstrappingKey<N>,
+    ) -> Self {
+        let mut c_prime = self.clone();
+
+        let two_n = 2 * N as u64;
+        assert_eq!(
+            c.modulus(),
+            two_n,
+            "the ciphertext must be switched to modulus 2N"
+        );
+        c_prime.rotate_trivial((two_n - c.body) % two_n);
+
+        // scratch space reused by every step
+        let mut next = c_prime.clone();
+        let mut rotated = c_prime.clone();
+        let mut g_inverse_buffer = bsk[0].0.g_inverse_buffer();
+
+        for (a, (ggsw_plus, ggsw_minus)) in c.mask.iter().zip(bsk) {
+            next.clone_from(&c_prime);
+            for (ggsw, exponent) in [(ggsw_plus, *a), (ggsw_minus, (two_n - a) % two_n)] {
+                rotated.clone_from(&c_prime);
+                rotated.rotate_assign(exponent);
+                rotated -= &c_prime;
+                ggsw.add_external_product_assign(&mut next, &rotated, &mut g_inverse_buffer);
+            }
+            std::mem::swap(&mut c_prime, &mut next);
+        }
+
+        c_prime
     }
 
-    /// Multiplies by the monomial `X^exponent` every component of `self`.
-    pub fn rotate(&self, exponent: u64) -> Self {
-        let mut res = Self::default();
-        for i in 0..k {
-            res.mask[i] = self.mask[i].multiply_by_monomial(exponent as usize);
+    /// Trivially encrypts the LUT polynomial of the identity over the messages of `params`.
+    pub fn trivial_encrypt_lut_poly(params: &Params) -> Self {
+        Self::trivial_encrypt_lut(params, |m| encode(m as u8, params))
+    }
+
+    /// Trivially encrypts the LUT polynomial mapping every message `m` of `params` (padding bit excluded)
+    /// to the torus value `f(m)`. Blind rotating it by a modswitched encryption of `m` puts `f(m)` in the constant coefficient.
+    pub fn trivial_encrypt_lut(params: &Params, f: impl Fn(u64) -> u64) -> Self {
+        Self {
+            body: lut_poly(params, f),
+            ..Self::zero(params.glwe_dim)
         }
+    }
+}
 
-        res.body = self.body.multiply_by_monomial(exponent as usize);
+/// Returns the LUT polynomial with coefficients `f(m)` in the box of every message `m` of `params` (padding bit excluded).
+pub fn lut_poly<const N: usize>(params: &Params, f: impl Fn(u64) -> u64) -> ResiduePoly<N> {
+    many_lut_poly(params, &[&f])
+}
 
-        res
+/// Returns the polynomial interleaving the LUTs of `fs`: in the box of every message `m`, the coefficients
+/// of degree `i` with `i = t mod fs.len()` hold `fs[t](m)`. `fs.len()` must be a power of two dividing the half box size `N/p`.
+///
+/// Blind rotating it by a multiple of `fs.len()` (see [`crate::server_key::ServerKey::many_lut_bootstrap`])
+/// puts `fs[t](m)` in the coefficient of degree `t`.
+pub fn many_lut_poly<const N: usize>(
+    params: &Params,
+    fs: &[&dyn Fn(u64) -> u64],
+) -> ResiduePoly<N> {
+    let p = params.plaintext_modulus() as usize;
+    let count = fs.len();
+    assert!(
+        count.is_power_of_two() && (N / p).is_multiple_of(count),
+        "the number of LUTs must be a power of two dividing N/p"
+    );
+
+    // one box of 2N/p coefficients per message, shifted by half a box to absorb the noise
+    let mut lut = ResiduePoly {
+        coefs: std::array::from_fn(|i| {
+            let m = (p * (i - i % count)) / (2 * N);
+            fs[i % count](m as u64)
+        }),
+    };
+    lut.multiply_by_monomial_assign(2 * N - N / p);
+    lut
+}
+
+impl<const N: usize, T: UnsignedTorus> AddAssign<&GlweCiphertext<N, T>> for GlweCiphertext<N, T> {
+    fn add_assign(&mut self, rhs: &GlweCiphertext<N, T>) {
+        for (a, b) in self.mask.iter_mut().zip(&rhs.mask) {
+            *a += b;
+        }
+        self.body += &rhs.body;
+    }
+}
+
+impl<const N: usize, T: UnsignedTorus> SubAssign<&GlweCiphertext<N, T>> for GlweCiphertext<N, T> {
+    fn sub_assign(&mut self, rhs: &GlweCiphertext<N, T>) {
+        for (a, b) in self.mask.iter_mut().zip(&rhs.mask) {
+            *a -= b;
+        }
+        self.body -= &rhs.body;
+    }
+}
+
+impl<const N: usize, T: UnsignedTorus> Neg for &GlweCiphertext<N, T> {
+    type Output = GlweCiphertext<N, T>;
+
+    fn neg(self) -> GlweCiphertext<N, T> {
+        GlweCiphertext {
+            mask: self.mask.iter().map(|a| -a).collect(),
+            body: -&self.body,
+        }
     }
+}
 
-    /// Trivially encrypts the LUT polynomial.
-    pub fn trivial_encrypt_lut_poly() -> Self {
-        // TODO: use iterator
-        let mut lut_coefs = [0u64; N];
+impl_ring_ops!([const N: usize, T: UnsignedTorus] GlweCiphertext<N, T>);
 
-        for i in 0..N {
-            lut_coefs[(i.wrapping_sub(64)) % N] = encode(((P * i) / (2 * N)).try_into().unwrap());
+impl<const N: usize, T: UnsignedTorus> MulAssign<T> for GlweCiphertext<N, T> {
+    fn mul_assign(&mut self, rhs: T) {
+        for a in self.mask.iter_mut() {
+            *a *= rhs;
         }
+        self.body *= rhs;
+    }
+}
 
-        Self {
-            body: ResiduePoly {
-                coefs: lut_coefs.to_vec(),
-            },
-            ..Default::default()
+impl_scalar_mul!([const N: usize, T: UnsignedTorus] GlweCiphertext<N, T>, T);
+
+impl<const N: usize, T: UnsignedTorus> MulAssign<&ResiduePoly<N, T>> for GlweCiphertext<N, T> {
+    fn mul_assign(&mut self, rhs: &ResiduePoly<N, T>) {
+        for a in self.mask.iter_mut() {
+            *a *= rhs;
         }
+        self.body *= rhs;
     }
 }
 
-impl SecretKey {
+impl_op_variants!([const N: usize, T: UnsignedTorus] GlweCiphertext<N, T>, ResiduePoly<N, T>, Mul, mul, MulAssign, mul_assign);
+
+impl<const N: usize> SecretKey<N> {
     /// Converts a GLWE secret key into a LWE secret key.
-    // TODO: generalize for k > 1
     pub fn recode(&self) -> LweSecretKey {
-        self.polys[0]
-            .coefs
+        self.polys
             .iter()
-            .copied()
-            .map(|coef| coef)
+            .flat_map(|poly| poly.coefs.iter().copied())
             .collect()
     }
 }
 
-impl Default for GlweCiphertext {
-    fn default() -> Self {
-        GlweCiphertext {
-            mask: vec![ResiduePoly::default(); k],
-            body: ResiduePoly::default(),
+/// Encrypts `sk1` under `sk2`, with the keyswitch decomposition of `params`.
+pub fn compute_glwe_ksk<const N: usize, T: UnsignedTorus>(
+    sk1: &SecretKey<N>,
+    sk2: &SecretKey<N>,
+    params: &Params,
+) -> GlweKeySwitchKey<N, T> {
+    let mut cts = Vec::<GlweCiphertext<N, T>>::with_capacity(sk1.polys.len() * params.ks.level);
+
+    for poly in &sk1.polys {
+        for j in 0..params.ks.level {
+            cts.push(GlweCiphertext::encrypt_poly(
+                &(poly.cast() * gadget::<T>(j, &params.ks)),
+                sk2,
+                params.glwe_std_dev,
+            ));
         }
     }
+
+    GlweKeySwitchKey {
+        decomp: params.ks,
+        cts,
+    }
 }
 
-pub fn keygen() -> SecretKey {
-    let polys: Vec<ResiduePoly> = (0..k).map(|_| ResiduePoly::get_random_bin()).collect();
+/// Generates a GLWE secret key. The degree `N` must match `params.poly_size`.
+pub fn keygen<const N: usize>(params: &Params) -> SecretKey<N> {
+    assert_eq!(params.poly_size, N, "polynomial size mismatch");
+
+    let polys: Vec<ResiduePoly<N>> = (0..params.glwe_dim)
+        .map(|_| match params.glwe_key_distribution {
+            SecretKeyDistribution::Binary => ResiduePoly::get_random_bin(),
+            SecretKeyDistribution::Ternary => ResiduePoly::get_random_ternary(),
+            SecretKeyDistribution::Gaussian(std_dev) => ResiduePoly::get_random_gaussian(std_dev),
+        })
+        .collect();
 
     SecretKey { polys }
 }
@@ -171,52 +451,56 @@ pub fn keygen() -> SecretKey {
 #[cfg(test)]
 mod tests {
     use crate::ggsw::compute_bsk;
-    use crate::glwe::{keygen, GlweCiphertext};
+    use crate::glwe::{compute_glwe_ksk, keygen, GlweCiphertext};
     use crate::lwe::{compute_ksk, lwe_keygen, LweCiphertext, LweSecretKey};
-    use crate::utils::{decode, decode_bootstrapped, encode};
+    use crate::params::{Params, FAST_TEST_INSECURE, MESSAGE_3};
+    use crate::poly::ResiduePoly;
+    use crate::utils::{decode, decode_bootstrapped, decode_poly, encode, encode_as, encode_poly};
     use rand::{thread_rng, Rng};
 
     #[test]
     #[ignore]
     fn test_bootstrapping() {
-        let sk1 = lwe_keygen();
-        let sk2 = keygen();
-        let bsk = compute_bsk(&sk1, &sk2); // list of encryptions under `sk2` of the bits of `sk1`.
-        let ksk = compute_ksk(&sk2.recode(), &sk1); // list of encryptions under `sk1` of the bits of `sk2`.
+        let params = MESSAGE_3;
+        let sk1 = lwe_keygen(&params);
+        let sk2 = keygen::<1024>(&params);
+        let bsk = compute_bsk(&sk1, &sk2, &params); // list of encryptions under `sk2` of the bits of `sk1`.
+        let ksk = compute_ksk(&sk2.recode(), &sk1, &params); // list of encryptions under `sk1` of the bits of `sk2`.
 
-        let lut = GlweCiphertext::trivial_encrypt_lut_poly();
+        let lut = GlweCiphertext::trivial_encrypt_lut_poly(&params);
 
         for _ in 0..16 {
             let msg = thread_rng().gen_range(0..8);
 
-            let c = LweCiphertext::encrypt(encode(msg), &sk1).modswitch(); // "noisy" ciphertext that will be bootstrapped
+            let c = LweCiphertext::encrypt(encode(msg, &params), &sk1, params.lwe_std_dev)
+                .modswitch((2 * params.poly_size).ilog2() as usize); // "noisy" ciphertext that will be bootstrapped
 
             let blind_rotated_lut = lut.blind_rotate(c, &bsk); // should return a GLWE encryption of X^{- \tilde{\mu}^*} * v(X) which should be equal to a polynomial with constant term \mu.
 
             let res = blind_rotated_lut
                 .sample_extract()
-                .keyswitch(&mut ksk.clone())
+                .keyswitch(&ksk)
                 .decrypt(&sk1);
 
-            let pt = decode_bootstrapped(res);
+            let pt = decode_bootstrapped(res, &params);
             assert_eq!(msg, pt)
         }
     }
 
-    /// This test fails from time to time.
-    /// It might be the case that B = 16, ell = 4 isn't suitable for keyswitching.
     #[test]
     fn test_keyswitching() {
-        let sk1 = lwe_keygen();
-        let sk2 = keygen();
-        let ksk = compute_ksk(&sk2.recode(), &sk1); // list of encryptions under `sk1` of the bits of `sk2`.
+        let params = MESSAGE_3;
+        let sk1 = lwe_keygen(&params);
+        let sk2 = keygen::<1024>(&params);
+        let ksk = compute_ksk(&sk2.recode(), &sk1, &params); // list of encryptions under `sk1` of the bits of `sk2`.
 
         for _ in 0..100 {
             let msg = thread_rng().gen_range(0..8);
-            let ct = GlweCiphertext::encrypt(encode(msg), &sk2).sample_extract();
-            let ks = ct.keyswitch(&mut ksk.clone());
+            let ct = GlweCiphertext::encrypt(encode(msg, &params), &sk2, params.glwe_std_dev)
+                .sample_extract();
+            let ks = ct.keyswitch(&ksk);
             let res = ks.decrypt(&sk1);
-            let pt = decode(res);
+            let pt = decode(res, &params);
 
             assert_eq!(msg, pt)
         }
@@ -224,53 +508,177 @@ mod tests {
 
     #[test]
     fn test_keygen_enc_dec() {
-        let sk = keygen();
+        let params = MESSAGE_3;
+        let sk = keygen::<1024>(&params);
         for _ in 0..100 {
             let msg = thread_rng().gen_range(0..16);
-            let ct = GlweCiphertext::encrypt(encode(msg), &sk);
-            let pt = decode(ct.decrypt(&sk));
+            let ct = GlweCiphertext::encrypt(encode(msg, &params), &sk, params.glwe_std_dev);
+            let pt = decode(ct.decrypt(&sk), &params);
             assert_eq!(pt, msg);
         }
     }
 
     #[test]
     fn test_add() {
-        let sk = keygen();
+        let params = MESSAGE_3;
+        let sk = keygen::<1024>(&params);
         for _ in 0..100 {
             let msg1 = thread_rng().gen_range(0..16);
             let msg2 = thread_rng().gen_range(0..16);
-            let ct1 = GlweCiphertext::encrypt(encode(msg1), &sk);
-            let ct2 = GlweCiphertext::encrypt(encode(msg2), &sk);
-            let res = ct1.add(&ct2);
-            let pt = decode(res.decrypt(&sk));
+            let ct1 = GlweCiphertext::encrypt(encode(msg1, &params), &sk, params.glwe_std_dev);
+            let ct2 = GlweCiphertext::encrypt(encode(msg2, &params), &sk, params.glwe_std_dev);
+            let res = &ct1 + &ct2;
+            let pt = decode(res.decrypt(&sk), &params);
             assert_eq!(pt, (msg1 + msg2) % 16);
         }
     }
 
     #[test]
     fn test_sub() {
-        let sk = keygen();
+        let params = MESSAGE_3;
+        let sk = keygen::<1024>(&params);
         for _ in 0..100 {
             let msg1 = thread_rng().gen_range(0..16);
             let msg2 = thread_rng().gen_range(0..16);
-            let ct1 = GlweCiphertext::encrypt(encode(msg1), &sk);
-            let ct2 = GlweCiphertext::encrypt(encode(msg2), &sk);
-            let res = ct1.sub(&ct2);
-            let pt = decode(res.decrypt(&sk));
+            let ct1 = GlweCiphertext::encrypt(encode(msg1, &params), &sk, params.glwe_std_dev);
+            let ct2 = GlweCiphertext::encrypt(encode(msg2, &params), &sk, params.glwe_std_dev);
+            let res = &ct1 - &ct2;
+            let pt = decode(res.decrypt(&sk), &params);
             assert_eq!(pt, (msg1.wrapping_sub(msg2)) % 16);
         }
     }
 
     #[test]
     fn test_sample_extract() {
-        let sk = keygen();
+        let params = MESSAGE_3;
+        let sk = keygen::<1024>(&params);
         let msg = thread_rng().gen_range(0..16);
-        let ct = GlweCiphertext::encrypt(encode(msg), &sk);
+        let ct = GlweCiphertext::encrypt(encode(msg, &params), &sk, params.glwe_std_dev);
 
         let sample_extracted: LweCiphertext = ct.sample_extract();
         let recoded_sk: LweSecretKey = sk.recode();
 
-        let pt = decode(sample_extracted.decrypt(&recoded_sk));
+        let pt = decode(sample_extracted.decrypt(&recoded_sk), &params);
         assert_eq!(pt, msg)
     }
+
+    #[test]
+    fn test_mul_plain() {
+        let params = MESSAGE_3;
+        let sk = keygen::<1024>(&params);
+        for _ in 0..100 {
+            let msg = thread_rng().gen_range(0..16);
+            let c: i64 = thread_rng().gen_range(-4..=4);
+            let ct = GlweCiphertext::encrypt(encode(msg, &params), &sk, params.glwe_std_dev);
+
+            // the constant coefficient of msg * (c + X^{N - 1}) is msg * c
+            let mut p = ResiduePoly::default();
+            p.coefs[0] = c as u64;
+            p.coefs[1023] = 1;
+
+            let pt = decode(ct.mul_plain(&p).decrypt(&sk), &params);
+            assert_eq!(pt as i64, (msg as i64 * c).rem_euclid(16));
+
+            let pt = decode((&ct * 3).decrypt(&sk), &params);
+            assert_eq!(pt, msg * 3 % 16);
+
+            let pt = decode(ct.multiply_constant(c).decrypt(&sk), &params);
+            assert_eq!(pt as i64, (msg as i64 * c).rem_euclid(16));
+        }
+    }
+
+    #[test]
+    fn test_poly_enc_dec() {
+        let params = MESSAGE_3;
+        let sk = keygen::<1024>(&params);
+        for _ in 0..10 {
+            let msgs: Vec<u8> = (0..1024).map(|_| thread_rng().gen_range(0..16)).collect();
+            let ct = GlweCiphertext::encrypt_poly(
+                &encode_poly(&msgs, &params),
+                &sk,
+                params.glwe_std_dev,
+            );
+            assert_eq!(decode_poly(&ct.decrypt_poly(&sk), &params), msgs);
+        }
+    }
+
+    #[test]
+    fn test_sample_extract_all() {
+        let params = MESSAGE_3;
+        let sk = keygen::<1024>(&params);
+        let recoded_sk: LweSecretKey = sk.recode();
+        let msgs: Vec<u8> = (0..1024).map(|_| thread_rng().gen_range(0..16)).collect();
+        let ct =
+            GlweCiphertext::encrypt_poly(&encode_poly(&msgs, &params), &sk, params.glwe_std_dev);
+
+        for (msg, lwe) in msgs.iter().zip(ct.sample_extract_all()) {
+            assert_eq!(decode(lwe.decrypt(&recoded_sk), &params), *msg);
+        }
+    }
+
+    #[test]
+    fn test_glwe_keyswitching() {
+        let params = FAST_TEST_INSECURE;
+        let params2 = Params {
+            glwe_dim: 2,
+            ..FAST_TEST_INSECURE
+        };
+        let sk1 = keygen::<256>(&params);
+        let sk2 = keygen::<256>(&params2);
+        let ksk = compute_glwe_ksk(&sk1, &sk2, &params);
+
+        let msgs: Vec<u8> = (0..256).map(|_| thread_rng().gen_range(0..16)).collect();
+        let ct =
+            GlweCiphertext::encrypt_poly(&encode_poly(&msgs, &params), &sk1, params.glwe_std_dev);
+
+        let ks = ct.keyswitch(&ksk);
+        assert_eq!(ks.mask.len(), 2);
+        assert_eq!(decode_poly(&ks.decrypt_poly(&sk2), &params), msgs);
+
+        let ks_back = ks.keyswitch(&compute_glwe_ksk(&sk2, &sk1, &params));
+        assert_eq!(decode_poly(&ks_back.decrypt_poly(&sk1), &params), msgs);
+    }
+
+    #[test]
+    fn test_glwe_modswitch() {
+        let params = MESSAGE_3;
+        let sk = keygen::<1024>(&params);
+        let log_modulus = 16;
+
+        let msgs: Vec<u8> = (0..1024).map(|_| thread_rng().gen_range(0..16)).collect();
+        let ct =
+            GlweCiphertext::encrypt_poly(&encode_poly(&msgs, &params), &sk, params.glwe_std_dev)
+                .modswitch(log_modulus);
+        assert!(ct.body.coefs.iter().all(|c| *c < 1 << log_modulus));
+
+        let mut phase = ct.decrypt_poly_modswitched(&sk, log_modulus);
+        for c in phase.coefs.iter_mut() {
+            *c <<= 64 - log_modulus;
+        }
+        assert_eq!(decode_poly(&phase, &params), msgs);
+    }
+
+    #[test]
+    fn test_u32_torus() {
+        let params = MESSAGE_3;
+        let sk1 = keygen::<1024>(&params);
+        let sk2 = keygen::<1024>(&params);
+        let ksk = compute_glwe_ksk::<1024, u32>(&sk1, &sk2, &params);
+
+        let msgs: Vec<u8> = (0..1024).map(|_| thread_rng().gen_range(0..16)).collect();
+        let pt = ResiduePoly::<1024, u32> {
+            coefs: std::array::from_fn(|i| encode_as(msgs[i], &params)),
+        };
+        let ct = GlweCiphertext::encrypt_poly(&pt, &sk1, params.glwe_std_dev);
+        assert_eq!(decode_poly(&ct.decrypt_poly(&sk1), &params), msgs);
+
+        let recoded_sk = sk1.recode();
+        for i in [0, 1, 511, 1023] {
+            let lwe = ct.sample_extract_at(i);
+            assert_eq!(decode(lwe.decrypt(&recoded_sk), &params), msgs[i]);
+        }
+
+        let ks = ct.keyswitch(&ksk);
+        assert_eq!(decode_poly(&ks.decrypt_poly(&sk2), &params), msgs);
+    }
 }
@@ -1,27 +1,19 @@
+#[macro_use]
+mod ops;
+
+pub mod circuit_bootstrapping;
+pub mod client_key;
+pub mod functional_ks;
 pub mod ggsw;
 pub mod glwe;
 pub mod lwe;
+pub mod multi_bit;
+pub mod packing;
+pub mod params;
 pub mod poly;
+pub mod prime;
+pub mod server_key;
+pub mod simd;
+pub mod torus;
 pub mod utils;
-
-/// Decomposition basis for the external product. This value is used implicitely.
-// pub const B: usize = 256;
-
-/// Ciphertext modulus. This value is used implicitely.
-// pub const Q: usize = 2^64;
-
-/// Plaintext modulus
-pub const P: usize = 16;
-
-/// Number of decomposition layers for the external product.
-pub const ELL: usize = 2;
-
-/// GLWE dimension
-#[allow(non_upper_case_globals)]
-pub const k: usize = 1;
-
-/// Degree `N` of irreducible polynomial X^N + 1
-pub const N: usize = 1024;
-
-/// Dimension of LWE ciphertexts
-pub const LWE_DIM: usize = 630;
+pub mod vertical_packing;
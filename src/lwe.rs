@@ -1,222 +1,346 @@
-use crate::{utils::round_value, LWE_DIM, N};
+use crate::params::{DecompositionParams, Params, SecretKeyDistribution};
+use crate::torus::UnsignedTorus;
+use crate::utils::{decompose, gadget, round_value, MAX_LEVEL};
 use rand::{thread_rng, Rng};
 use rand_distr::{Distribution, Normal};
 use serde::{Deserialize, Serialize};
+use std::ops::{AddAssign, MulAssign, Neg, SubAssign};
 
 #[derive(Clone, Serialize, Deserialize)]
-pub struct LweCiphertext {
+#[serde(bound = "")]
+pub struct LweCiphertext<T: UnsignedTorus = u64> {
+    pub mask: Vec<T>,
+    pub body: T,
+}
+
+/// Small signed coefficients stored as `u64`, reduced modulo q when used with other ciphertext moduli.
+pub type LweSecretKey = Vec<u64>;
+
+/// LWE ciphertext with modulus `2^log_modulus`, output by [`LweCiphertext::modswitch`].
+/// Coefficients are in `[0, 2^log_modulus)`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ModSwitchedLweCiphertext {
+    pub log_modulus: usize,
     pub mask: Vec<u64>,
     pub body: u64,
 }
 
-pub type LweSecretKey = Vec<u64>;
-pub type KeySwitchingKey = Vec<LweCiphertext>;
+/// Encryptions of the gadget decomposition of an input key under an output key.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct KeySwitchingKey<T: UnsignedTorus = u64> {
+    pub decomp: DecompositionParams,
+    pub cts: Vec<LweCiphertext<T>>,
+}
 
-impl LweCiphertext {
-    pub fn encrypt(mu: u64, sk: &LweSecretKey) -> LweCiphertext {
-        let sigma = f64::powf(2.0, 49.0);
+impl<T: UnsignedTorus> LweCiphertext<T> {
+    /// Encrypts `mu` under `sk` with a noise of standard deviation `std_dev` (on the torus).
+    pub fn encrypt(mu: T, sk: &LweSecretKey, std_dev: f64) -> Self {
+        let sigma = std_dev * T::modulus_f64();
         let normal = Normal::new(0.0, sigma).unwrap();
 
         let e = normal.sample(&mut rand::thread_rng()).round() as i64;
-        let mu_star = mu.wrapping_add_signed(e);
+        let mu_star = mu.wrapping_add(T::from_i64(e));
 
-        let mask: Vec<u64> = (0..LWE_DIM).map(|_| rand::random::<u64>()).collect();
+        let mask: Vec<T> = (0..sk.len()).map(|_| T::random()).collect();
 
-        let mut body = 0u64;
-        for i in 0..LWE_DIM {
-            if sk[i] == 1 {
-                body = body.wrapping_add(mask[i]);
-            }
-        }
-
-        body = body.wrapping_add(mu_star);
+        let body = T::key_dot_product(&mask, sk).wrapping_add(mu_star);
 
         LweCiphertext { mask, body }
     }
 
-    pub fn decrypt(self, sk: &LweSecretKey) -> u64 {
-        let mut body: u64 = 0u64;
-        for i in 0..sk.len() {
-            if sk[i] == 1 {
-                body = body.wrapping_add(self.mask[i]);
-            }
-        }
+    pub fn decrypt(self, sk: &LweSecretKey) -> T {
+        self.body.wrapping_sub(T::key_dot_product(&self.mask, sk)) // mu_star
+    }
 
-        self.body.wrapping_sub(body) // mu_star
+    /// Multiplies the encrypted message by the signed integer `constant`.
+    /// The noise is multiplied by `|constant|`.
+    pub fn multiply_constant(&self, constant: i64) -> Self {
+        let mut res = self.clone();
+        res.multiply_constant_assign(constant);
+        res
     }
 
-    pub fn decrypt_modswitched(self, sk: &LweSecretKey) -> u64 {
-        let mut dot_prod = 0u64;
-        for i in 0..LWE_DIM {
-            if sk[i] == 1 {
-                dot_prod = (dot_prod + self.mask[i]) % (2 * LWE_DIM as u64);
-            }
+    /// In-place version of [`LweCiphertext::multiply_constant`].
+    pub fn multiply_constant_assign(&mut self, constant: i64) -> &mut Self {
+        let constant = T::from_i64(constant);
+        for a in self.mask.iter_mut() {
+            *a = a.wrapping_mul(constant);
         }
+        self.body = self.body.wrapping_mul(constant);
 
-        self.body.wrapping_sub(dot_prod) % (2 * LWE_DIM as u64) // mu_star
+        self
     }
 
-    pub fn add(self, rhs: Self) -> Self {
-        let mask = self
-            .mask
-            .iter()
-            .zip(rhs.mask)
-            .map(|(a, b)| a.wrapping_add(b))
-            .collect();
-
-        let body = self.body.wrapping_add(rhs.body);
+    /// Switch from ciphertext modulus `q` to `2^log_modulus`, rounding every coefficient to the nearest.
+    /// The blind rotation expects `2^log_modulus = 2N`.
+    pub fn modswitch(&self, log_modulus: usize) -> ModSwitchedLweCiphertext {
+        let switch = |a: T| round_value(a, log_modulus) % (1 << log_modulus);
 
-        LweCiphertext { mask, body }
+        ModSwitchedLweCiphertext {
+            log_modulus,
+            mask: self.mask.iter().map(|a| switch(*a)).collect(),
+            body: switch(self.body),
+        }
     }
 
-    pub fn sub(self, rhs: &Self) -> Self {
-        let mask = self
-            .mask
-            .iter()
-            .zip(&rhs.mask)
-            .map(|(a, b)| a.wrapping_sub(*b))
-            .collect();
-
-        let body = self.body.wrapping_sub(rhs.body);
+    /// Switch to the key encrypted by `ksk`.
+    /// This reduces the dimension of the ciphertext.
+    pub fn keyswitch(&self, ksk: &KeySwitchingKey<T>) -> Self {
+        let mut keyswitched = LweCiphertext::trivial_encrypt(self.body, ksk.cts[0].mask.len());
+
+        let mut digits = [T::ZERO; MAX_LEVEL];
+        for (a, cts) in self.mask.iter().zip(ksk.cts.chunks(ksk.decomp.level)) {
+            decompose(*a, &ksk.decomp, &mut digits);
+            for (d, ct) in digits.into_iter().zip(cts) {
+                T::sub_scaled_slice(&mut keyswitched.mask, &ct.mask, d);
+                keyswitched.body = keyswitched.body.wrapping_sub(ct.body.wrapping_mul(d));
+            }
+        }
 
-        LweCiphertext { mask, body }
+        keyswitched
     }
 
-    pub fn multiply_constant_assign(&mut self, constant: u64) -> &mut Self {
-        self.mask = self.mask.iter().map(|a| a.wrapping_mul(constant)).collect();
-
-        self.body = self.body.wrapping_mul(constant);
-
-        self
+    /// Trivially encrypts `mu` with a mask of dimension `dim`.
+    pub fn trivial_encrypt(mu: T, dim: usize) -> Self {
+        LweCiphertext {
+            mask: vec![T::ZERO; dim],
+            body: mu,
+        }
     }
+}
 
-    /// Switch from ciphertext modulus `2^64` to `2N` (implicit `N = 1024`).
-    pub fn modswitch(&self) -> Self {
-        let mask = self.mask.iter().map(|a| ((a >> 52) + 1) >> 1).collect();
+impl ModSwitchedLweCiphertext {
+    pub fn modulus(&self) -> u64 {
+        1 << self.log_modulus
+    }
 
-        let body = ((self.body >> 52) + 1) >> 1;
+    /// Returns the phase of `self`, modulo `2^log_modulus`.
+    pub fn decrypt(&self, sk: &LweSecretKey) -> u64 {
+        let mut dot_prod = 0u64;
+        for (a, s) in self.mask.iter().zip(sk) {
+            dot_prod = dot_prod.wrapping_add(a.wrapping_mul(*s));
+        }
 
-        LweCiphertext { mask, body }
+        self.body.wrapping_sub(dot_prod) % self.modulus() // mu_star
     }
 
-    /// Switch to the key encrypted by `ksk`.
-    /// This reduces the dimension of the ciphertext.
-    // TODO: generalize for k > 1
-    pub fn keyswitch(&self, ksk: &mut KeySwitchingKey) -> Self {
-        let mut keyswitched = LweCiphertext {
-            body: self.body,
-            ..Default::default()
-        };
-
-        for i in (0..4 * N).step_by(4) {
-            let decomp = decomposition_4_4(self.mask[i / 4]);
-            keyswitched = keyswitched
-                .sub(ksk[i].multiply_constant_assign(decomp[0]))
-                .sub(ksk[i + 1].multiply_constant_assign(decomp[1]))
-                .sub(ksk[i + 2].multiply_constant_assign(decomp[2]))
-                .sub(ksk[i + 3].multiply_constant_assign(decomp[3]));
+    /// Multiplies by `2^(log_modulus - self.log_modulus)` to switch to the larger modulus `2^log_modulus`.
+    /// The coefficients of the result are multiples of that factor.
+    pub fn lift(&self, log_modulus: usize) -> Self {
+        assert!(
+            log_modulus >= self.log_modulus,
+            "cannot lift a ciphertext modulo 2^{} to the smaller modulus 2^{log_modulus}",
+            self.log_modulus
+        );
+        let shift = log_modulus - self.log_modulus;
+
+        ModSwitchedLweCiphertext {
+            log_modulus,
+            mask: self.mask.iter().map(|a| a << shift).collect(),
+            body: self.body << shift,
         }
-
-        keyswitched
     }
 }
 
-impl Default for LweCiphertext {
-    fn default() -> Self {
-        LweCiphertext {
-            mask: vec![0u64; LWE_DIM],
-            body: 0u64,
-        }
+impl<T: UnsignedTorus> AddAssign<&LweCiphertext<T>> for LweCiphertext<T> {
+    fn add_assign(&mut self, rhs: &LweCiphertext<T>) {
+        T::add_assign_slice(&mut self.mask, &rhs.mask);
+        self.body = self.body.wrapping_add(rhs.body);
     }
 }
 
-/// Approximate decomposition with lg(B) = 4 and ell = 4.
-/// Takes a polynomial coefficient in Z_{2^64} and decomposes its 16 MSBs in 4 integers in `[-8, 7] as u64`.
-pub fn decomposition_4_4(val: u64) -> [u64; 4] {
-    let mut ret = [0u64; 4];
-    let rounded_val = round_value(val);
+impl<T: UnsignedTorus> SubAssign<&LweCiphertext<T>> for LweCiphertext<T> {
+    fn sub_assign(&mut self, rhs: &LweCiphertext<T>) {
+        T::sub_assign_slice(&mut self.mask, &rhs.mask);
+        self.body = self.body.wrapping_sub(rhs.body);
+    }
+}
 
-    let mut carry = 0u64;
-    for i in 0..4 {
-        let mut res = ((rounded_val >> (4 * i)) & 0x0F) + carry;
+impl<T: UnsignedTorus> Neg for &LweCiphertext<T> {
+    type Output = LweCiphertext<T>;
 
-        let carry_bit = res & 8;
+    fn neg(self) -> LweCiphertext<T> {
+        LweCiphertext {
+            mask: self.mask.iter().map(|a| a.wrapping_neg()).collect(),
+            body: self.body.wrapping_neg(),
+        }
+    }
+}
 
-        res = res.wrapping_sub(carry_bit << 1);
-        ret[i] = res;
+impl_ring_ops!([T: UnsignedTorus] LweCiphertext<T>);
 
-        carry = carry_bit >> 3;
+impl<T: UnsignedTorus> MulAssign<T> for LweCiphertext<T> {
+    fn mul_assign(&mut self, rhs: T) {
+        for a in self.mask.iter_mut() {
+            *a = a.wrapping_mul(rhs);
+        }
+        self.body = self.body.wrapping_mul(rhs);
     }
-
-    ret
 }
 
-pub fn lwe_keygen() -> LweSecretKey {
-    let mut sk = Vec::<u64>::with_capacity(LWE_DIM);
-    for _ in 0..LWE_DIM {
-        sk.push(thread_rng().gen_range(0..=1));
+impl_scalar_mul!([T: UnsignedTorus] LweCiphertext<T>, T);
+
+pub fn lwe_keygen(params: &Params) -> LweSecretKey {
+    let mut sk = Vec::<u64>::with_capacity(params.lwe_dim);
+    for _ in 0..params.lwe_dim {
+        sk.push(match params.lwe_key_distribution {
+            SecretKeyDistribution::Binary => thread_rng().gen_range(0..=1),
+            SecretKeyDistribution::Ternary => thread_rng().gen_range(-1i64..=1) as u64,
+            SecretKeyDistribution::Gaussian(std_dev) => {
+                let normal = Normal::new(0.0, std_dev).unwrap();
+                normal.sample(&mut thread_rng()).round() as i64 as u64
+            }
+        });
     }
 
     sk
 }
 
 /// Encrypts `sk1` under `sk2`.
-// TODO: generalize for k > 1
-pub fn compute_ksk(sk1: &LweSecretKey, sk2: &LweSecretKey) -> KeySwitchingKey {
-    let mut ksk = Vec::<LweCiphertext>::with_capacity(4 * N);
-
-    for bit in sk1.iter().take(N) {
-        // 4 layers in the decomposition for the KSK
-        for j in 0..4 {
-            let mu = bit << (48 + (4 * j)); // lg(B) = 4
-            ksk.push(LweCiphertext::encrypt(mu, sk2));
+pub fn compute_ksk<T: UnsignedTorus>(
+    sk1: &LweSecretKey,
+    sk2: &LweSecretKey,
+    params: &Params,
+) -> KeySwitchingKey<T> {
+    let mut cts = Vec::<LweCiphertext<T>>::with_capacity(sk1.len() * params.ks.level);
+
+    for bit in sk1 {
+        for j in 0..params.ks.level {
+            let mu = T::from_i64(*bit as i64).wrapping_mul(gadget(j, &params.ks));
+            cts.push(LweCiphertext::encrypt(mu, sk2, params.lwe_std_dev));
         }
     }
-    ksk
+
+    KeySwitchingKey {
+        decomp: params.ks,
+        cts,
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::lwe::{lwe_keygen, LweCiphertext};
-    use crate::utils::{decode, encode};
+    use crate::lwe::{compute_ksk, lwe_keygen, LweCiphertext};
+    use crate::params::MESSAGE_3;
+    use crate::utils::{decode, encode, encode_as, round_value};
     use rand::{thread_rng, Rng};
 
     #[test]
     fn test_keygen_enc_dec() {
-        let sk = lwe_keygen();
+        let params = MESSAGE_3;
+        let sk = lwe_keygen(&params);
         for _ in 0..100 {
             let msg = thread_rng().gen_range(0..16);
-            let ct = LweCiphertext::encrypt(encode(msg), &sk);
-            let pt = decode(ct.decrypt(&sk));
+            let ct = LweCiphertext::encrypt(encode(msg, &params), &sk, params.lwe_std_dev);
+            let pt = decode(ct.decrypt(&sk), &params);
             assert_eq!(pt, msg);
         }
     }
 
     #[test]
     fn test_add() {
-        let sk = lwe_keygen();
+        let params = MESSAGE_3;
+        let sk = lwe_keygen(&params);
         for _ in 0..100 {
             let msg1 = thread_rng().gen_range(0..16);
             let msg2 = thread_rng().gen_range(0..16);
-            let ct1 = LweCiphertext::encrypt(encode(msg1), &sk);
-            let ct2 = LweCiphertext::encrypt(encode(msg2), &sk);
-            let res = ct1.add(ct2);
-            let pt = decode(res.decrypt(&sk));
+            let ct1 = LweCiphertext::encrypt(encode(msg1, &params), &sk, params.lwe_std_dev);
+            let ct2 = LweCiphertext::encrypt(encode(msg2, &params), &sk, params.lwe_std_dev);
+            let pt = decode((&ct1 * 3 + &ct2).decrypt(&sk), &params);
+            assert_eq!(pt, (3 * msg1 + msg2) % 16);
+            let res = ct1 + ct2;
+            let pt = decode(res.decrypt(&sk), &params);
             assert_eq!(pt, (msg1 + msg2) % 16);
         }
     }
 
     #[test]
     fn test_sub() {
-        let sk = lwe_keygen();
+        let params = MESSAGE_3;
+        let sk = lwe_keygen(&params);
         for _ in 0..100 {
             let msg1 = thread_rng().gen_range(0..16);
             let msg2 = thread_rng().gen_range(0..16);
-            let ct1 = LweCiphertext::encrypt(encode(msg1), &sk);
-            let ct2 = LweCiphertext::encrypt(encode(msg2), &sk);
-            let res = ct1.sub(&ct2);
-            let pt = decode(res.decrypt(&sk));
+            let ct1 = LweCiphertext::encrypt(encode(msg1, &params), &sk, params.lwe_std_dev);
+            let ct2 = LweCiphertext::encrypt(encode(msg2, &params), &sk, params.lwe_std_dev);
+            let res = ct1 - &ct2;
+            let pt = decode(res.decrypt(&sk), &params);
             assert_eq!(pt, (msg1.wrapping_sub(msg2)) % 16);
         }
     }
+
+    #[test]
+    fn test_dot_product() {
+        let params = MESSAGE_3;
+        let sk = lwe_keygen(&params);
+        for _ in 0..100 {
+            let msgs: Vec<u8> = (0..4).map(|_| thread_rng().gen_range(0..16)).collect();
+            let weights: Vec<i64> = (0..4).map(|_| thread_rng().gen_range(-4..=4)).collect();
+
+            let res = msgs
+                .iter()
+                .zip(&weights)
+                .map(|(msg, w)| {
+                    LweCiphertext::encrypt(encode(*msg, &params), &sk, params.lwe_std_dev)
+                        .multiply_constant(*w)
+                })
+                .reduce(|acc, ct| acc + ct)
+                .unwrap();
+
+            let expected = msgs
+                .iter()
+                .zip(&weights)
+                .map(|(msg, w)| *msg as i64 * w)
+                .sum::<i64>()
+                .rem_euclid(16);
+            let pt = decode(res.decrypt(&sk), &params);
+            assert_eq!(pt as i64, expected);
+        }
+    }
+
+    #[test]
+    fn test_modswitch() {
+        let params = MESSAGE_3;
+        let sk = lwe_keygen(&params);
+        for log_modulus in [11, 16, 32] {
+            for _ in 0..100 {
+                let msg = thread_rng().gen_range(0..16);
+                let ct = LweCiphertext::encrypt(encode(msg, &params), &sk, params.lwe_std_dev)
+                    .modswitch(log_modulus);
+                assert_eq!(ct.modulus(), 1 << log_modulus);
+
+                // even for 2^11 = 2N, the modswitch noise stays well below half a box
+                let pt = round_value(ct.decrypt(&sk) << (64 - log_modulus), 4) % 16;
+                assert_eq!(pt, msg as u64);
+            }
+        }
+    }
+
+    #[test]
+    fn test_u32_torus() {
+        let params = MESSAGE_3;
+        let sk1 = lwe_keygen(&params);
+        let sk2 = lwe_keygen(&params);
+        let ksk = compute_ksk::<u32>(&sk1, &sk2, &params);
+
+        for _ in 0..100 {
+            let msg1 = thread_rng().gen_range(0..16);
+            let msg2 = thread_rng().gen_range(0..16);
+            let ct1 =
+                LweCiphertext::<u32>::encrypt(encode_as(msg1, &params), &sk1, params.lwe_std_dev);
+            let ct2 =
+                LweCiphertext::<u32>::encrypt(encode_as(msg2, &params), &sk1, params.lwe_std_dev);
+            assert_eq!(decode(ct1.clone().decrypt(&sk1), &params), msg1);
+
+            let res = (ct1 + ct2).keyswitch(&ksk);
+            assert_eq!(
+                decode(res.clone().decrypt(&sk2), &params),
+                (msg1 + msg2) % 16
+            );
+
+            let switched = res.modswitch(16);
+            let pt = round_value(switched.decrypt(&sk2) << 48, 4) % 16;
+            assert_eq!(pt, ((msg1 + msg2) % 16) as u64);
+        }
+    }
 }
@@ -0,0 +1,147 @@
+use crate::ggsw::GgswCiphertext;
+use crate::glwe::{GlweCiphertext, SecretKey};
+use crate::lwe::{LweSecretKey, ModSwitchedLweCiphertext};
+use crate::params::Params;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Bootstrapping key grouping the LWE key bits by `grouping_factor`.
+///
+/// For a group of bits `(s_0, ..., s_{g-1})`, the key holds an encryption of
+/// `f_b(s) = prod_i (s_i if b_i == 1 else 1 - s_i)` for every non-null `b` in `{0, 1}^g`.
+/// The last group is smaller if `grouping_factor` does not divide the LWE dimension.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MultiBitBootstrappingKey<const N: usize> {
+    pub grouping_factor: usize,
+    pub groups: Vec<Vec<GgswCiphertext<N>>>,
+}
+
+/// Encrypts the products of the groups of bits of `s` under `sk`.
+/// `grouping_factor` must be 2 or 3.
+pub fn compute_multi_bit_bsk<const N: usize>(
+    s: &LweSecretKey,
+    sk: &SecretKey<N>,
+    params: &Params,
+    grouping_factor: usize,
+) -> MultiBitBootstrappingKey<N> {
+    assert!(
+        (2..=3).contains(&grouping_factor),
+        "the grouping factor must be 2 or 3, got {grouping_factor}"
+    );
+
+    let groups = s
+        .chunks(grouping_factor)
+        .map(|bits| {
+            (1..1usize << bits.len())
+                .map(|b| {
+                    let f_b = bits
+                        .iter()
+                        .enumerate()
+                        .map(|(i, s_i)| if (b >> i) & 1 == 1 { *s_i } else { 1 - s_i })
+                        .product::<u64>();
+                    GgswCiphertext::encrypt(f_b as u8, sk, params.glwe_std_dev, &params.pbs)
+                })
+                .collect()
+        })
+        .collect();
+
+    MultiBitBootstrappingKey {
+        grouping_factor,
+        groups,
+    }
+}
+
+impl<const N: usize> GlweCiphertext<N> {
+    /// Performs the blind rotation of `self` with one step per group of LWE key bits.
+    /// Each step computes `ACC + sum_b GGSW(f_b(s)) * (X^{<a, b>} * ACC - ACC)` which is equal to `X^{<a, s>} * ACC`.
+    // `self` is assumed to be a trivial encryption
+    pub fn multi_bit_blind_rotate(
+        &self,
+        c: ModSwitchedLweCiphertext,
+        bsk: &MultiBitBootstrappingKey<N>,
+    ) -> Self {
+        let two_n = 2 * N as u64;
+        assert_eq!(
+            c.modulus(),
+            two_n,
+            "the ciphertext must be switched to modulus 2N"
+        );
+        let mut acc = self.rotate((two_n - c.body) % two_n);
+
+        // scratch space reused by every step
+        #[cfg(not(feature = "parallel"))]
+        let (mut next, mut rotated, mut g_inverse_buffer) = (
+            acc.clone(),
+            acc.clone(),
+            bsk.groups[0][0].g_inverse_buffer(),
+        );
+
+        for (a, ggsws) in c.mask.chunks(bsk.grouping_factor).zip(&bsk.groups) {
+            let exponent = |b: usize| {
+                a.iter()
+                    .enumerate()
+                    .filter(|(i, _)| (b >> i) & 1 == 1)
+                    .fold(0, |acc, (_, a_i)| (acc + a_i) % two_n)
+            };
+
+            #[cfg(feature = "parallel")]
+            {
+                let sum = (1..1usize << a.len())
+                    .into_par_iter()
+                    .zip(ggsws.par_iter())
+                    .map(|(b, ggsw)| ggsw.external_product(&(acc.rotate(exponent(b)) - &acc)))
+                    .reduce_with(|x, y| x + y)
+                    .unwrap();
+                acc += sum;
+            }
+
+            #[cfg(not(feature = "parallel"))]
+            {
+                next.clone_from(&acc);
+                for (b, ggsw) in (1..1usize << a.len()).zip(ggsws) {
+                    rotated.clone_from(&acc);
+                    rotated.rotate_assign(exponent(b));
+                    rotated -= &acc;
+                    ggsw.add_external_product_assign(&mut next, &rotated, &mut g_inverse_buffer);
+                }
+                std::mem::swap(&mut acc, &mut next);
+            }
+        }
+
+        acc
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::glwe::{keygen, GlweCiphertext};
+    use crate::lwe::{lwe_keygen, LweCiphertext};
+    use crate::multi_bit::compute_multi_bit_bsk;
+    use crate::params::FAST_TEST_INSECURE;
+    use crate::utils::{decode_bootstrapped, encode};
+
+    #[test]
+    fn test_multi_bit_blind_rotate() {
+        let params = FAST_TEST_INSECURE;
+        let sk1 = lwe_keygen(&params);
+        let sk2 = keygen::<256>(&params);
+        let lut = GlweCiphertext::trivial_encrypt_lut_poly(&params);
+
+        for grouping_factor in [2, 3] {
+            let bsk = compute_multi_bit_bsk(&sk1, &sk2, &params, grouping_factor);
+
+            for msg in 0..params.message_modulus as u8 {
+                let c = LweCiphertext::encrypt(encode(msg, &params), &sk1, params.lwe_std_dev)
+                    .modswitch((2 * params.poly_size).ilog2() as usize);
+
+                let res = lut
+                    .multi_bit_blind_rotate(c, &bsk)
+                    .sample_extract()
+                    .decrypt(&sk2.recode());
+
+                assert_eq!(msg, decode_bootstrapped(res, &params));
+            }
+        }
+    }
+}
@@ -0,0 +1,84 @@
+/// Derives the owned and borrowed forms of a binary operator from its `OpAssign<&Rhs>` implementation:
+/// `OpAssign<Rhs>`, and `Op<Rhs>` / `Op<&Rhs>` for both `T` and `&T`.
+/// Owned left operands are updated in place, borrowed ones are cloned first.
+macro_rules! impl_op_variants {
+    ([$($gen:tt)*] $t:ty, $rhs:ty, $op_trait:ident, $op:ident, $op_assign_trait:ident, $op_assign:ident) => {
+        impl<$($gen)*> std::ops::$op_assign_trait<$rhs> for $t {
+            fn $op_assign(&mut self, rhs: $rhs) {
+                std::ops::$op_assign_trait::$op_assign(self, &rhs)
+            }
+        }
+
+        impl<$($gen)*> std::ops::$op_trait<&$rhs> for $t {
+            type Output = $t;
+
+            fn $op(mut self, rhs: &$rhs) -> $t {
+                std::ops::$op_assign_trait::$op_assign(&mut self, rhs);
+                self
+            }
+        }
+
+        impl<$($gen)*> std::ops::$op_trait<$rhs> for $t {
+            type Output = $t;
+
+            fn $op(self, rhs: $rhs) -> $t {
+                std::ops::$op_trait::$op(self, &rhs)
+            }
+        }
+
+        impl<$($gen)*> std::ops::$op_trait<&$rhs> for &$t {
+            type Output = $t;
+
+            fn $op(self, rhs: &$rhs) -> $t {
+                std::ops::$op_trait::$op(self.clone(), rhs)
+            }
+        }
+
+        impl<$($gen)*> std::ops::$op_trait<$rhs> for &$t {
+            type Output = $t;
+
+            fn $op(self, rhs: $rhs) -> $t {
+                std::ops::$op_trait::$op(self.clone(), &rhs)
+            }
+        }
+    };
+}
+
+/// Derives every form of `Add`, `Sub` and `Neg` of a ciphertext or polynomial type from
+/// its `AddAssign<&T>`, `SubAssign<&T>` and `Neg for &T` implementations.
+macro_rules! impl_ring_ops {
+    ([$($gen:tt)*] $t:ty) => {
+        impl_op_variants!([$($gen)*] $t, $t, Add, add, AddAssign, add_assign);
+        impl_op_variants!([$($gen)*] $t, $t, Sub, sub, SubAssign, sub_assign);
+
+        impl<$($gen)*> std::ops::Neg for $t {
+            type Output = $t;
+
+            fn neg(self) -> $t {
+                -&self
+            }
+        }
+    };
+}
+
+/// Derives `Mul<S>` for `T` and `&T` from a `MulAssign<S>` implementation, `S` being a scalar type.
+macro_rules! impl_scalar_mul {
+    ([$($gen:tt)*] $t:ty, $s:ty) => {
+        impl<$($gen)*> std::ops::Mul<$s> for $t {
+            type Output = $t;
+
+            fn mul(mut self, rhs: $s) -> $t {
+                self *= rhs;
+                self
+            }
+        }
+
+        impl<$($gen)*> std::ops::Mul<$s> for &$t {
+            type Output = $t;
+
+            fn mul(self, rhs: $s) -> $t {
+                self.clone() * rhs
+            }
+        }
+    };
+}
@@ -0,0 +1,123 @@
+use crate::glwe::{GlweCiphertext, SecretKey};
+use crate::lwe::{LweCiphertext, LweSecretKey};
+use crate::params::{DecompositionParams, Params};
+use crate::poly::ResiduePoly;
+use crate::torus::UnsignedTorus;
+use crate::utils::{decompose, gadget, MAX_LEVEL};
+use serde::{Deserialize, Serialize};
+
+/// Encryptions under a GLWE key of the gadget decomposition of a LWE key,
+/// laid out as in [`crate::lwe::KeySwitchingKey`].
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PackingKeySwitchKey<const N: usize> {
+    pub decomp: DecompositionParams,
+    pub cts: Vec<GlweCiphertext<N>>,
+}
+
+/// Encrypts `s` under `sk`, with the keyswitch decomposition of `params`.
+pub fn compute_packing_ksk<const N: usize>(
+    s: &LweSecretKey,
+    sk: &SecretKey<N>,
+    params: &Params,
+) -> PackingKeySwitchKey<N> {
+    let mut cts = Vec::<GlweCiphertext<N>>::with_capacity(s.len() * params.ks.level);
+
+    for coef in s {
+        for j in 0..params.ks.level {
+            let mu = coef.wrapping_mul(gadget(j, &params.ks));
+            cts.push(GlweCiphertext::encrypt(mu, sk, params.glwe_std_dev));
+        }
+    }
+
+    PackingKeySwitchKey {
+        decomp: params.ks,
+        cts,
+    }
+}
+
+/// Packs the messages of `cts` into a single GLWE ciphertext, the `i`-th message being the coefficient of degree `i`.
+/// At most `N` ciphertexts can be packed, the remaining coefficients encrypt zero.
+pub fn pack_lwe_into_glwe<const N: usize>(
+    cts: &[LweCiphertext],
+    pksk: &PackingKeySwitchKey<N>,
+) -> GlweCiphertext<N> {
+    assert!(
+        cts.len() <= N,
+        "too many ciphertexts for a polynomial of size {N}"
+    );
+
+    let glwe_dim = pksk.cts[0].mask.len();
+    let mut res = GlweCiphertext::zero(glwe_dim);
+    let mut keyswitched = GlweCiphertext::zero(glwe_dim);
+
+    for (i, ct) in cts.iter().enumerate() {
+        keyswitch_to_glwe(ct, pksk, &mut keyswitched);
+        keyswitched.rotate_assign(i as u64);
+        res += &keyswitched;
+    }
+
+    res
+}
+
+/// Switches `ct` to a GLWE encryption of the same message (as the constant coefficient), written to `res`.
+fn keyswitch_to_glwe<const N: usize>(
+    ct: &LweCiphertext,
+    pksk: &PackingKeySwitchKey<N>,
+    res: &mut GlweCiphertext<N>,
+) {
+    for a in res.mask.iter_mut() {
+        *a = ResiduePoly::default();
+    }
+    res.body = ResiduePoly::default();
+    res.body.coefs[0] = ct.body;
+
+    let mut digits = [0u64; MAX_LEVEL];
+    for (a, ksk_cts) in ct.mask.iter().zip(pksk.cts.chunks(pksk.decomp.level)) {
+        decompose(*a, &pksk.decomp, &mut digits);
+        for (d, ksk_ct) in digits.into_iter().zip(ksk_cts) {
+            for (x, y) in res.mask.iter_mut().zip(&ksk_ct.mask) {
+                sub_scaled_assign(x, y, d);
+            }
+            sub_scaled_assign(&mut res.body, &ksk_ct.body, d);
+        }
+    }
+}
+
+/// Computes `x -= d * y` in place.
+pub(crate) fn sub_scaled_assign<const N: usize>(
+    x: &mut ResiduePoly<N>,
+    y: &ResiduePoly<N>,
+    d: u64,
+) {
+    u64::sub_scaled_slice(&mut x.coefs, &y.coefs, d);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::glwe::keygen;
+    use crate::lwe::{lwe_keygen, LweCiphertext};
+    use crate::packing::{compute_packing_ksk, pack_lwe_into_glwe};
+    use crate::params::FAST_TEST_INSECURE;
+    use crate::utils::{decode_poly, encode};
+    use rand::{thread_rng, Rng};
+
+    #[test]
+    fn test_pack_lwe_into_glwe() {
+        let params = FAST_TEST_INSECURE;
+        let lwe_sk = lwe_keygen(&params);
+        let glwe_sk = keygen::<256>(&params);
+        let pksk = compute_packing_ksk(&lwe_sk, &glwe_sk, &params);
+
+        let msgs: Vec<u8> = (0..100).map(|_| thread_rng().gen_range(0..16)).collect();
+        let cts: Vec<LweCiphertext> = msgs
+            .iter()
+            .map(|msg| LweCiphertext::encrypt(encode(*msg, &params), &lwe_sk, params.lwe_std_dev))
+            .collect();
+
+        let packed = pack_lwe_into_glwe(&cts, &pksk);
+        let pt = decode_poly(&packed.decrypt_poly(&glwe_sk), &params);
+
+        assert_eq!(pt[..msgs.len()], msgs);
+        assert!(pt[msgs.len()..].iter().all(|m| *m == 0));
+    }
+}
@@ -0,0 +1,378 @@
+use serde::{Deserialize, Serialize};
+
+/// Parameters of a gadget decomposition with basis `B = 2^base_log` and `level` digits.
+/// Only the `base_log * level` MSBs of a coefficient are decomposed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DecompositionParams {
+    pub base_log: usize,
+    pub level: usize,
+}
+
+/// Distribution of the coefficients of a secret key.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum SecretKeyDistribution {
+    /// Uniform in `{0, 1}`.
+    Binary,
+    /// Uniform in `{-1, 0, 1}`.
+    Ternary,
+    /// Rounded Gaussian of the given standard deviation (on the integers).
+    Gaussian(f64),
+}
+
+impl SecretKeyDistribution {
+    /// Expectation of the square of a key coefficient.
+    pub fn mean_square(&self) -> f64 {
+        match self {
+            SecretKeyDistribution::Binary => 0.5,
+            SecretKeyDistribution::Ternary => 2.0 / 3.0,
+            SecretKeyDistribution::Gaussian(std_dev) => std_dev * std_dev,
+        }
+    }
+}
+
+/// Order of the keyswitch and the blind rotation in a bootstrap.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PbsOrder {
+    /// Ciphertexts at rest are under the LWE key: blind rotation, sample extraction then keyswitch.
+    BootstrapKeyswitch,
+    /// Ciphertexts at rest are under the (recoded) GLWE key: keyswitch, blind rotation then sample extraction.
+    KeyswitchBootstrap,
+}
+
+/// Full set of parameters used by keygen, encoding and bootstrapping.
+///
+/// Standard deviations are expressed on the torus, i.e. relative to the ciphertext modulus `q = 2^64`.
+/// Failure probabilities documented on the presets are given by [`Params::log2_p_fail`].
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Params {
+    /// Number of messages that can be bootstrapped.
+    pub message_modulus: u64,
+    /// Number of carry values that fit above the message. The plaintext space also has a padding bit.
+    pub carry_modulus: u64,
+    /// Dimension `n` of LWE ciphertexts.
+    pub lwe_dim: usize,
+    /// GLWE dimension `k`.
+    pub glwe_dim: usize,
+    /// Degree `N` of irreducible polynomial X^N + 1.
+    pub poly_size: usize,
+    pub lwe_std_dev: f64,
+    pub glwe_std_dev: f64,
+    /// Distribution of the LWE key. Bootstrapping requires a binary or ternary key.
+    pub lwe_key_distribution: SecretKeyDistribution,
+    pub glwe_key_distribution: SecretKeyDistribution,
+    /// Decomposition used by the external product (bootstrapping key).
+    pub pbs: DecompositionParams,
+    /// Decomposition used by the keyswitch.
+    pub ks: DecompositionParams,
+    /// Order of the keyswitch and the blind rotation, which selects the key of ciphertexts at rest.
+    pub pbs_order: PbsOrder,
+    /// Estimated security level in bits.
+    pub security_level: u32,
+}
+
+/// Boolean gates (1-bit messages).
+///
+/// * security: 128 bits
+/// * failure probability: 2^-298
+/// * bootstrapping key size: 76 MiB
+pub const BOOLEAN: Params = Params {
+    message_modulus: 2,
+    carry_modulus: 1,
+    lwe_dim: 722,
+    glwe_dim: 2,
+    poly_size: 512,
+    lwe_std_dev: 0.000013071021089943935,
+    glwe_std_dev: 0.00000004990272175010415,
+    lwe_key_distribution: SecretKeyDistribution::Binary,
+    glwe_key_distribution: SecretKeyDistribution::Binary,
+    pbs: DecompositionParams {
+        base_log: 6,
+        level: 3,
+    },
+    ks: DecompositionParams {
+        base_log: 3,
+        level: 4,
+    },
+    pbs_order: PbsOrder::BootstrapKeyswitch,
+    security_level: 128,
+};
+
+/// 2-bit messages with a 2-bit carry.
+///
+/// * security: 128 bits
+/// * failure probability: 2^-40
+/// * bootstrapping key size: 46 MiB
+pub const MESSAGE_2_CARRY_2: Params = Params {
+    message_modulus: 4,
+    carry_modulus: 4,
+    lwe_dim: 742,
+    glwe_dim: 1,
+    poly_size: 2048,
+    lwe_std_dev: 0.000007069849454709433,
+    glwe_std_dev: 0.00000000000000029403601535432533,
+    lwe_key_distribution: SecretKeyDistribution::Binary,
+    glwe_key_distribution: SecretKeyDistribution::Binary,
+    pbs: DecompositionParams {
+        base_log: 23,
+        level: 1,
+    },
+    ks: DecompositionParams {
+        base_log: 3,
+        level: 5,
+    },
+    pbs_order: PbsOrder::BootstrapKeyswitch,
+    security_level: 128,
+};
+
+/// 3-bit messages. Lattice parameters of the original TFHE library, formerly the only set of this crate.
+///
+/// * security: about 110 bits (the parameters predate recent attacks)
+/// * failure probability: 2^-34
+/// * bootstrapping key size: 59 MiB
+pub const MESSAGE_3: Params = Params {
+    message_modulus: 8,
+    carry_modulus: 1,
+    lwe_dim: 630,
+    glwe_dim: 1,
+    poly_size: 1024,
+    lwe_std_dev: 3.0517578125e-5,        // 2^-15
+    glwe_std_dev: 2.9802322387695312e-8, // 2^-25
+    lwe_key_distribution: SecretKeyDistribution::Binary,
+    glwe_key_distribution: SecretKeyDistribution::Binary,
+    pbs: DecompositionParams {
+        base_log: 7,
+        level: 3,
+    },
+    ks: DecompositionParams {
+        base_log: 2,
+        level: 8,
+    },
+    pbs_order: PbsOrder::BootstrapKeyswitch,
+    security_level: 110,
+};
+
+/// 4-bit messages. Same lattice parameters as [`MESSAGE_2_CARRY_2`].
+///
+/// * security: 128 bits
+/// * failure probability: 2^-40
+/// * bootstrapping key size: 46 MiB
+pub const MESSAGE_4: Params = Params {
+    message_modulus: 16,
+    carry_modulus: 1,
+    ..MESSAGE_2_CARRY_2
+};
+
+/// Small and insecure parameters with 3-bit messages. Only meant for tests.
+///
+/// * security: none
+/// * failure probability: 2^-134
+/// * bootstrapping key size: 512 KiB
+pub const FAST_TEST_INSECURE: Params = Params {
+    message_modulus: 8,
+    carry_modulus: 1,
+    lwe_dim: 32,
+    glwe_dim: 1,
+    poly_size: 256,
+    lwe_std_dev: 9.094947017729282e-13,  // 2^-40
+    glwe_std_dev: 9.094947017729282e-13, // 2^-40
+    lwe_key_distribution: SecretKeyDistribution::Binary,
+    glwe_key_distribution: SecretKeyDistribution::Binary,
+    pbs: DecompositionParams {
+        base_log: 10,
+        level: 2,
+    },
+    ks: DecompositionParams {
+        base_log: 4,
+        level: 4,
+    },
+    pbs_order: PbsOrder::BootstrapKeyswitch,
+    security_level: 0,
+};
+
+/// Decompositions used by circuit bootstrapping, on top of the [`Params`] of the server key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CircuitBootstrappingParams {
+    /// Decomposition of the output GGSW ciphertexts.
+    pub cbs: DecompositionParams,
+    /// Decomposition used by the private functional keyswitch.
+    pub pfks: DecompositionParams,
+}
+
+/// Circuit bootstrapping decompositions for [`FAST_TEST_INSECURE`]. Only meant for tests.
+pub const FAST_TEST_INSECURE_CBS: CircuitBootstrappingParams = CircuitBootstrappingParams {
+    cbs: DecompositionParams {
+        base_log: 4,
+        level: 3,
+    },
+    pfks: DecompositionParams {
+        base_log: 12,
+        level: 3,
+    },
+};
+
+/// Every preset, along with the name it can be selected with.
+pub const PRESETS: [(&str, Params); 5] = [
+    ("boolean", BOOLEAN),
+    ("message_2_carry_2", MESSAGE_2_CARRY_2),
+    ("message_3", MESSAGE_3),
+    ("message_4", MESSAGE_4),
+    ("fast_test_insecure", FAST_TEST_INSECURE),
+];
+
+impl Params {
+    /// Selects a preset by name (see [`PRESETS`]).
+    pub fn from_name(name: &str) -> Option<Self> {
+        PRESETS
+            .iter()
+            .find(|(preset_name, _)| *preset_name == name)
+            .map(|(_, params)| *params)
+    }
+
+    /// Size of the plaintext space, padding bit included.
+    pub fn plaintext_modulus(&self) -> u64 {
+        2 * self.message_modulus * self.carry_modulus
+    }
+
+    /// Scaling factor `q / plaintext_modulus` of encoded messages.
+    pub fn delta(&self) -> u64 {
+        1u64 << (64 - self.plaintext_modulus().ilog2())
+    }
+
+    /// Size in bytes of the bootstrapping key.
+    pub fn bootstrapping_key_size(&self) -> usize {
+        let k = self.glwe_dim;
+        let ggsw_size = (k + 1) * self.pbs.level * (k + 1) * self.poly_size * 8;
+
+        // a ternary key holds two GGSW ciphertexts per coefficient
+        let ggsw_count = match self.lwe_key_distribution {
+            SecretKeyDistribution::Ternary => 2 * self.lwe_dim,
+            _ => self.lwe_dim,
+        };
+
+        ggsw_count * ggsw_size
+    }
+
+    /// Size in bytes of the keyswitching key.
+    pub fn keyswitching_key_size(&self) -> usize {
+        self.glwe_dim * self.poly_size * self.ks.level * (self.lwe_dim + 1) * 8
+    }
+
+    /// Variance of the noise of a ciphertext output by the blind rotation.
+    pub fn pbs_variance(&self) -> f64 {
+        let k = self.glwe_dim as f64;
+        let big_n = self.poly_size as f64;
+        let level = self.pbs.level as f64;
+        let base = (1u64 << self.pbs.base_log) as f64;
+
+        let key_noise =
+            (k + 1.0) * level * big_n * (base * base + 2.0) / 12.0 * self.glwe_std_dev.powi(2);
+        let rounding = (1.0 + k * big_n * self.glwe_key_distribution.mean_square())
+            / (12.0 * base.powf(2.0 * level));
+
+        // a ternary key needs two external products per coefficient
+        let external_products = match self.lwe_key_distribution {
+            SecretKeyDistribution::Ternary => 2 * self.lwe_dim,
+            _ => self.lwe_dim,
+        };
+
+        external_products as f64 * (key_noise + rounding)
+    }
+
+    /// Variance of the noise added by the keyswitch.
+    pub fn ks_variance(&self) -> f64 {
+        let input_dim = (self.glwe_dim * self.poly_size) as f64;
+        let level = self.ks.level as f64;
+        let base = (1u64 << self.ks.base_log) as f64;
+
+        let key_noise = input_dim * level * (base * base + 2.0) / 12.0 * self.lwe_std_dev.powi(2);
+        let rounding =
+            input_dim * self.glwe_key_distribution.mean_square() / (12.0 * base.powf(2.0 * level));
+
+        key_noise + rounding
+    }
+
+    /// Variance of the noise added by the switch to modulus `2N`.
+    pub fn modswitch_variance(&self) -> f64 {
+        let big_n = self.poly_size as f64;
+        (1.0 + self.lwe_dim as f64 * self.lwe_key_distribution.mean_square())
+            / (48.0 * big_n * big_n)
+    }
+
+    /// Variance of the noise of a ciphertext at rest output by a bootstrap.
+    pub fn output_variance(&self) -> f64 {
+        match self.pbs_order {
+            PbsOrder::BootstrapKeyswitch => self.pbs_variance() + self.ks_variance(),
+            PbsOrder::KeyswitchBootstrap => self.pbs_variance(),
+        }
+    }
+
+    /// Variance of the noise at the input of the blind rotation when bootstrapping a linear combination
+    /// of ciphertexts output by a bootstrap, with weights of squared 2-norm `squared_norm`.
+    /// With [`PbsOrder::KeyswitchBootstrap`], the keyswitch noise is added after the linear combination.
+    pub fn blind_rotation_input_variance(&self, squared_norm: f64) -> f64 {
+        let keyswitch = match self.pbs_order {
+            PbsOrder::BootstrapKeyswitch => 0.0,
+            PbsOrder::KeyswitchBootstrap => self.ks_variance(),
+        };
+
+        squared_norm * self.output_variance() + keyswitch + self.modswitch_variance()
+    }
+
+    /// Base 2 logarithm of the probability that bootstrapping a bootstrapped ciphertext returns a wrong message.
+    pub fn log2_p_fail(&self) -> f64 {
+        self.log2_p_fail_with_norm(1.0)
+    }
+
+    /// Base 2 logarithm of the probability that bootstrapping a linear combination of bootstrapped ciphertexts
+    /// returns a wrong message, see [`Params::blind_rotation_input_variance`].
+    pub fn log2_p_fail_with_norm(&self, squared_norm: f64) -> f64 {
+        let variance = self.blind_rotation_input_variance(squared_norm);
+        let half_box = 0.5 / self.plaintext_modulus() as f64;
+
+        log2_erfc(half_box / (2.0 * variance).sqrt())
+    }
+}
+
+/// Base 2 logarithm of the complementary error function, for `x >= 0`.
+/// Uses the Chebyshev approximation from Numerical Recipes (relative error below 1.2e-7).
+fn log2_erfc(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.5 * x);
+    let poly = -1.26551223
+        + t * (1.00002368
+            + t * (0.37409196
+                + t * (0.09678418
+                    + t * (-0.18628806
+                        + t * (0.27886807
+                            + t * (-1.13520398
+                                + t * (1.48851587 + t * (-0.82215223 + t * 0.17087277))))))));
+
+    (t.ln() - x * x + poly) / std::f64::consts::LN_2
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::params::{Params, PbsOrder, PRESETS};
+
+    #[test]
+    fn test_presets() {
+        for (name, params) in PRESETS {
+            assert_eq!(Params::from_name(name), Some(params));
+            assert!(params.log2_p_fail() < -30.0, "{name}");
+        }
+        assert_eq!(Params::from_name("unknown"), None);
+    }
+
+    #[test]
+    fn test_pbs_order_noise() {
+        for (_, params) in PRESETS {
+            let ks_pbs = Params {
+                pbs_order: PbsOrder::KeyswitchBootstrap,
+                ..params
+            };
+
+            // the keyswitch noise is only added once after a linear combination
+            assert!((ks_pbs.log2_p_fail() - params.log2_p_fail()).abs() < 1e-6);
+            assert!(ks_pbs.log2_p_fail_with_norm(16.0) < params.log2_p_fail_with_norm(16.0));
+        }
+    }
+}
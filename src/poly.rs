@@ -1,131 +1,159 @@
-use crate::N;
 use rand::{thread_rng, Rng};
+use rand_distr::{Distribution, Normal};
 use serde::{Deserialize, Serialize};
+use serde_big_array::BigArray;
+use std::ops::{AddAssign, MulAssign, Neg, SubAssign};
 
-/// Represents an element of Z_{q}\[X\]/(X^N + 1) with implicit q = 2^64.
+use crate::torus::UnsignedTorus;
+
+/// Represents an element of Z_{q}\[X\]/(X^N + 1) with implicit q = 2^64, or the modulus of another [`UnsignedTorus`].
+/// Coefficients are stored inline, so that polynomials live on the stack.
 #[derive(Clone, Serialize, Deserialize)]
-pub struct ResiduePoly {
-    pub coefs: Vec<u64>,
+pub struct ResiduePoly<const N: usize, T: UnsignedTorus = u64> {
+    #[serde(with = "BigArray")]
+    pub coefs: [T; N],
 }
 
-impl ResiduePoly {
-    pub fn new() -> Self {
-        ResiduePoly {
-            coefs: Vec::<u64>::with_capacity(N),
-        }
-    }
-
-    pub fn add(&self, rhs: &ResiduePoly) -> Self {
-        let mut res = Self::default();
-        for i in 0..N {
-            res.coefs[i] = self.coefs[i].wrapping_add(rhs.coefs[i]);
-        }
+impl<const N: usize, T: UnsignedTorus> ResiduePoly<N, T> {
+    pub fn add_constant(&self, constant: T) -> Self {
+        let mut res = self.clone();
+        res.add_constant_assign(constant);
         res
     }
 
-    pub fn add_assign(&mut self, rhs: &ResiduePoly) {
-        for i in 0..N {
-            self.coefs[i] = self.coefs[i].wrapping_add(rhs.coefs[i]);
-        }
+    pub fn add_constant_assign(&mut self, constant: T) {
+        self.coefs[0] = self.coefs[0].wrapping_add(constant);
     }
 
-    pub fn add_constant(&self, constant: u64) -> Self {
-        let mut res: ResiduePoly = self.clone();
-        res.coefs[0] = res.coefs[0].wrapping_add(constant);
-        res
+    /// Adds `lhs * rhs` to `self`, see [`UnsignedTorus::add_negacyclic_mul`].
+    pub fn add_mul_assign(&mut self, lhs: &ResiduePoly<N, T>, rhs: &ResiduePoly<N, T>) {
+        T::add_negacyclic_mul(&mut self.coefs, &lhs.coefs, &rhs.coefs);
     }
 
-    pub fn add_constant_assign(&mut self, constant: u64) {
-        self.coefs[0] = self.coefs[0].wrapping_add(constant);
+    /// Generates a residue polynomial with random coefficients in \[0..q)
+    pub fn get_random() -> Self {
+        Self {
+            coefs: std::array::from_fn(|_| T::random()),
+        }
     }
 
-    pub fn sub(&self, rhs: &ResiduePoly) -> Self {
-        let mut res = Self::default();
-        for i in 0..N {
-            res.coefs[i] = self.coefs[i].wrapping_sub(rhs.coefs[i]);
+    /// Generates a residue polynomial with random coefficients in \[0..1\]
+    pub fn get_random_bin() -> Self {
+        Self {
+            coefs: std::array::from_fn(|_| T::from_u64(thread_rng().gen_range(0..=1))),
         }
-        res
     }
 
-    // TODO: use FFT for better performances
-    pub fn mul(&self, rhs: &ResiduePoly) -> Self {
-        let mut coefs = Vec::<u64>::with_capacity(N);
-        for i in 0..N {
-            let mut coef = 0u64;
-            for j in 0..i + 1 {
-                coef = coef.wrapping_add(self.coefs[j].wrapping_mul(rhs.coefs[i - j]));
-            }
-            for j in i + 1..N {
-                coef = coef.wrapping_sub(self.coefs[j].wrapping_mul(rhs.coefs[N - j + i]));
-            }
-            coefs.push(coef);
+    /// Generates a residue polynomial with random coefficients in \[-1..1\]
+    pub fn get_random_ternary() -> Self {
+        Self {
+            coefs: std::array::from_fn(|_| T::from_i64(thread_rng().gen_range(-1..=1))),
         }
-        ResiduePoly { coefs }
     }
 
-    /// Generates a residue polynomial with random coefficients in \[0..2^64)
-    pub fn get_random() -> Self {
-        let coefs = (0..N).map(|_| rand::random::<u64>()).collect();
-
-        Self { coefs }
+    /// Generates a residue polynomial with rounded Gaussian coefficients of standard deviation `std_dev`
+    pub fn get_random_gaussian(std_dev: f64) -> Self {
+        let normal = Normal::new(0.0, std_dev).unwrap();
+        Self {
+            coefs: std::array::from_fn(|_| {
+                T::from_i64(normal.sample(&mut thread_rng()).round() as i64)
+            }),
+        }
     }
 
-    /// Generates a residue polynomial with random coefficients in \[0..1\]
-    pub fn get_random_bin() -> Self {
-        let coefs = (0..N).map(|_| thread_rng().gen_range(0..=1)).collect();
-
-        Self { coefs }
+    /// Reduces every coefficient modulo the torus size of `U`.
+    /// Small signed values, such as secret key coefficients, are kept as is.
+    pub fn cast<U: UnsignedTorus>(&self) -> ResiduePoly<N, U> {
+        ResiduePoly {
+            coefs: self.coefs.map(|c| U::from_i64(c.to_i64())),
+        }
     }
 
     /// Multiplies the residue polynomial by X^{exponent} = X^{2N + exponent}.
     /// `exponent` is assumed to be reduced modulo 2N.
     pub fn multiply_by_monomial(&self, exponent: usize) -> Self {
-        let mut rotated_coefs = Vec::<u64>::with_capacity(N);
+        let mut res = self.clone();
+        res.multiply_by_monomial_assign(exponent);
+        res
+    }
 
+    /// In-place version of [`ResiduePoly::multiply_by_monomial`].
+    pub fn multiply_by_monomial_assign(&mut self, exponent: usize) {
         let reverse = exponent >= N;
         let exponent = exponent % N;
 
-        for i in 0..N {
-            rotated_coefs.push({
-                if i < exponent {
-                    if reverse {
-                        self.coefs[i + N - exponent]
-                    } else {
-                        self.coefs[i + N - exponent].wrapping_neg()
-                    }
-                } else if reverse {
-                    self.coefs[i - exponent].wrapping_neg()
-                } else {
-                    self.coefs[i - exponent]
-                }
-            })
-        }
+        self.coefs.rotate_right(exponent);
 
-        ResiduePoly {
-            coefs: rotated_coefs,
-        }
+        // coefficients that wrapped around are negated, all of the others if X^N = -1 was factored out
+        let (wrapped, shifted) = self.coefs.split_at_mut(exponent);
+        T::neg_assign_slice(if reverse { shifted } else { wrapped });
     }
 }
 
-impl Default for ResiduePoly {
+impl<const N: usize, T: UnsignedTorus> Default for ResiduePoly<N, T> {
     fn default() -> Self {
         ResiduePoly {
-            coefs: vec![0u64; N],
+            coefs: [T::ZERO; N],
         }
     }
 }
 
+impl<const N: usize, T: UnsignedTorus> AddAssign<&ResiduePoly<N, T>> for ResiduePoly<N, T> {
+    fn add_assign(&mut self, rhs: &ResiduePoly<N, T>) {
+        T::add_assign_slice(&mut self.coefs, &rhs.coefs);
+    }
+}
+
+impl<const N: usize, T: UnsignedTorus> SubAssign<&ResiduePoly<N, T>> for ResiduePoly<N, T> {
+    fn sub_assign(&mut self, rhs: &ResiduePoly<N, T>) {
+        T::sub_assign_slice(&mut self.coefs, &rhs.coefs);
+    }
+}
+
+impl<const N: usize, T: UnsignedTorus> Neg for &ResiduePoly<N, T> {
+    type Output = ResiduePoly<N, T>;
+
+    fn neg(self) -> ResiduePoly<N, T> {
+        let mut res = self.clone();
+        T::neg_assign_slice(&mut res.coefs);
+        res
+    }
+}
+
+impl_ring_ops!([const N: usize, T: UnsignedTorus] ResiduePoly<N, T>);
+
+// TODO: use FFT for better performances
+impl<const N: usize, T: UnsignedTorus> MulAssign<&ResiduePoly<N, T>> for ResiduePoly<N, T> {
+    fn mul_assign(&mut self, rhs: &ResiduePoly<N, T>) {
+        let lhs = std::mem::take(self);
+        self.add_mul_assign(&lhs, rhs);
+    }
+}
+
+impl_op_variants!([const N: usize, T: UnsignedTorus] ResiduePoly<N, T>, ResiduePoly<N, T>, Mul, mul, MulAssign, mul_assign);
+
+impl<const N: usize, T: UnsignedTorus> MulAssign<T> for ResiduePoly<N, T> {
+    fn mul_assign(&mut self, rhs: T) {
+        for a in self.coefs.iter_mut() {
+            *a = a.wrapping_mul(rhs);
+        }
+    }
+}
+
+impl_scalar_mul!([const N: usize, T: UnsignedTorus] ResiduePoly<N, T>, T);
+
 #[cfg(test)]
 mod tests {
     use rand::{thread_rng, Rng};
 
-    use crate::{poly::ResiduePoly, N};
+    use crate::poly::ResiduePoly;
 
     #[test]
     /// Tests that the monomial multiplication is coherent with monomial multiplication.
     fn test_monomial_mult() {
+        const N: usize = 1024;
         for _ in 0..1000 {
-            let mut monomial_coefs = vec![0u64; N];
+            let mut monomial_coefs = [0u64; N];
             let monomial_non_null_term = thread_rng().gen_range(0..2 * N);
 
             if monomial_non_null_term < 1024 {
@@ -138,12 +166,28 @@ mod tests {
                 coefs: monomial_coefs,
             };
 
-            let polynomial = ResiduePoly::get_random();
+            let polynomial = ResiduePoly::<N>::get_random();
 
-            let res_mul = polynomial.mul(&monomial);
+            let res_mul = &polynomial * &monomial;
             let res_monomial_mul = polynomial.multiply_by_monomial(monomial_non_null_term);
 
             assert_eq!(res_mul.coefs, res_monomial_mul.coefs);
         }
     }
+
+    #[test]
+    fn test_ops() {
+        const N: usize = 1024;
+        let p = ResiduePoly::<N>::get_random();
+        let q = ResiduePoly::<N>::get_random();
+
+        assert_eq!((&p + &q - &q).coefs, p.coefs);
+        assert_eq!((-&p + &p).coefs, [0u64; N]);
+        assert_eq!((&p * 3).coefs, (&p + &p + &p).coefs);
+        assert_eq!((&p * &q).coefs, (&q * &p).coefs);
+
+        let mut one = ResiduePoly::<N>::default();
+        one.add_constant_assign(1);
+        assert_eq!((&p * one).coefs, p.coefs);
+    }
 }
@@ -0,0 +1,274 @@
+use crate::torus::UnsignedTorus;
+use rand::{thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+
+/// The Solinas prime `q = 2^64 - 2^32 + 1`, for which `2^64 = 2^32 - 1 mod q`.
+pub const SOLINAS_PRIME: u64 = 0xFFFF_FFFF_0000_0001;
+
+/// `2^64 mod q`.
+const EPSILON: u64 = 0xFFFF_FFFF;
+
+/// Generator of the multiplicative group of Z_{q}.
+const GENERATOR: u64 = 7;
+
+/// Element of Z_{q} for the NTT-friendly prime [`SOLINAS_PRIME`], in canonical form `[0, q)`.
+/// Since `2^32` divides `q - 1`, negacyclic products are computed exactly with a NTT for `N <= 2^31`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SolinasPrime(u64);
+
+impl SolinasPrime {
+    /// Reduces a 128-bit integer modulo q, using `2^64 = 2^32 - 1` and `2^96 = -1`.
+    fn reduce(x: u128) -> Self {
+        let lo = x as u64;
+        let hi = (x >> 64) as u64;
+        let (hi_hi, hi_lo) = (hi >> 32, hi & EPSILON);
+
+        let (mut t0, borrow) = lo.overflowing_sub(hi_hi);
+        if borrow {
+            t0 -= EPSILON; // cannot underflow since t0 >= 2^64 - 2^32
+        }
+
+        let (mut t1, carry) = t0.overflowing_add(hi_lo * EPSILON);
+        if carry {
+            t1 += EPSILON; // cannot overflow since t1 < (2^32 - 1)^2
+        }
+
+        Self::from_u64(t1)
+    }
+
+    fn pow(self, mut exponent: u64) -> Self {
+        let mut base = self;
+        let mut res = Self::ONE;
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                res = res.wrapping_mul(base);
+            }
+            base = base.wrapping_mul(base);
+            exponent >>= 1;
+        }
+        res
+    }
+
+    fn inverse(self) -> Self {
+        self.pow(SOLINAS_PRIME - 2)
+    }
+}
+
+impl UnsignedTorus for SolinasPrime {
+    const BITS: u32 = 64;
+    const ZERO: Self = SolinasPrime(0);
+    const ONE: Self = SolinasPrime(1);
+
+    fn wrapping_add(self, rhs: Self) -> Self {
+        let (sum, carry) = self.0.overflowing_add(rhs.0);
+        if carry {
+            // sum + 2^64 = sum + EPSILON, which is below q
+            SolinasPrime(sum + EPSILON)
+        } else {
+            Self::from_u64(sum)
+        }
+    }
+
+    fn wrapping_sub(self, rhs: Self) -> Self {
+        self.wrapping_add(rhs.wrapping_neg())
+    }
+
+    fn wrapping_mul(self, rhs: Self) -> Self {
+        Self::reduce(self.0 as u128 * rhs.0 as u128)
+    }
+
+    fn wrapping_neg(self) -> Self {
+        if self.0 == 0 {
+            self
+        } else {
+            SolinasPrime(SOLINAS_PRIME - self.0)
+        }
+    }
+
+    fn from_u64(x: u64) -> Self {
+        SolinasPrime(if x >= SOLINAS_PRIME {
+            x - SOLINAS_PRIME
+        } else {
+            x
+        })
+    }
+
+    fn from_i64(x: i64) -> Self {
+        let abs = Self::from_u64(x.unsigned_abs());
+        if x < 0 {
+            abs.wrapping_neg()
+        } else {
+            abs
+        }
+    }
+
+    fn to_u64(self) -> u64 {
+        self.0
+    }
+
+    fn to_i64(self) -> i64 {
+        if self.0 > SOLINAS_PRIME / 2 {
+            -((SOLINAS_PRIME - self.0) as i64)
+        } else {
+            self.0 as i64
+        }
+    }
+
+    fn random() -> Self {
+        SolinasPrime(thread_rng().gen_range(0..SOLINAS_PRIME))
+    }
+
+    fn modulus_f64() -> f64 {
+        SOLINAS_PRIME as f64
+    }
+
+    fn from_fraction(num: u64, log_den: u32) -> Self {
+        let q = SOLINAS_PRIME as u128;
+        let rounded = (num as u128 * q + (1 << log_den >> 1)) >> log_den;
+        SolinasPrime((rounded % q) as u64)
+    }
+
+    fn round_to_bits(self, bits: u32) -> u64 {
+        assert!(bits < Self::BITS, "cannot round to {bits} bits");
+        let q = SOLINAS_PRIME as u128;
+        ((((self.0 as u128) << bits) + q / 2) / q) as u64
+    }
+
+    fn add_negacyclic_mul<const N: usize>(acc: &mut [Self; N], lhs: &[Self; N], rhs: &[Self; N]) {
+        // a(X) * b(X) mod X^N + 1 is a cyclic product of a(psi X) and b(psi X), psi being a 2N-th root of unity
+        let psi = SolinasPrime(GENERATOR).pow((SOLINAS_PRIME - 1) / (2 * N as u64));
+        let omega = psi.wrapping_mul(psi);
+
+        let mut twisted_lhs = *lhs;
+        let mut twisted_rhs = *rhs;
+        let mut psi_i = Self::ONE;
+        for (a, b) in twisted_lhs.iter_mut().zip(twisted_rhs.iter_mut()) {
+            *a = a.wrapping_mul(psi_i);
+            *b = b.wrapping_mul(psi_i);
+            psi_i = psi_i.wrapping_mul(psi);
+        }
+
+        ntt(&mut twisted_lhs, omega);
+        ntt(&mut twisted_rhs, omega);
+        for (a, b) in twisted_lhs.iter_mut().zip(&twisted_rhs) {
+            *a = a.wrapping_mul(*b);
+        }
+        ntt(&mut twisted_lhs, omega.inverse());
+
+        // untwist, and divide by N for the inverse transform
+        let psi_inv = psi.inverse();
+        let mut scale = SolinasPrime(N as u64).inverse();
+        for (res, c) in acc.iter_mut().zip(&twisted_lhs) {
+            *res = res.wrapping_add(c.wrapping_mul(scale));
+            scale = scale.wrapping_mul(psi_inv);
+        }
+    }
+}
+
+/// In-place cyclic NTT of `a`, `omega` being a primitive root of unity of order `a.len()`.
+fn ntt(a: &mut [SolinasPrime], omega: SolinasPrime) {
+    let n = a.len();
+
+    // bit-reversal permutation, then iterative Cooley-Tukey butterflies
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j ^= bit;
+        if i < j {
+            a.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let omega_len = omega.pow((n / len) as u64);
+        for chunk in a.chunks_mut(len) {
+            let (low, high) = chunk.split_at_mut(len / 2);
+            let mut w = SolinasPrime::ONE;
+            for (x, y) in low.iter_mut().zip(high.iter_mut()) {
+                let u = *x;
+                let v = y.wrapping_mul(w);
+                *x = u.wrapping_add(v);
+                *y = u.wrapping_sub(v);
+                w = w.wrapping_mul(omega_len);
+            }
+        }
+        len <<= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::glwe::{compute_glwe_ksk, keygen, GlweCiphertext};
+    use crate::lwe::{compute_ksk, lwe_keygen, LweCiphertext};
+    use crate::params::MESSAGE_3;
+    use crate::poly::ResiduePoly;
+    use crate::prime::{SolinasPrime, SOLINAS_PRIME};
+    use crate::torus::{schoolbook_add_mul, UnsignedTorus};
+    use crate::utils::{decode, decode_poly, encode_as};
+    use rand::{thread_rng, Rng};
+
+    #[test]
+    fn test_arithmetic() {
+        let q = SOLINAS_PRIME as u128;
+        for _ in 0..1000 {
+            let (a, b) = (SolinasPrime::random(), SolinasPrime::random());
+            let (x, y) = (a.to_u64() as u128, b.to_u64() as u128);
+
+            assert_eq!(a.wrapping_add(b).to_u64() as u128, (x + y) % q);
+            assert_eq!(a.wrapping_sub(b).to_u64() as u128, (x + q - y) % q);
+            assert_eq!(a.wrapping_mul(b).to_u64() as u128, x * y % q);
+            assert_eq!(a.inverse().wrapping_mul(a), SolinasPrime::ONE);
+        }
+        assert_eq!(SolinasPrime::from_i64(-3).to_i64(), -3);
+    }
+
+    #[test]
+    fn test_ntt_mul() {
+        let p = ResiduePoly::<1024, SolinasPrime>::get_random();
+        let q = ResiduePoly::<1024, SolinasPrime>::get_random();
+
+        let mut expected = ResiduePoly::default();
+        schoolbook_add_mul(&mut expected.coefs, &p.coefs, &q.coefs);
+        assert_eq!((&p * &q).coefs, expected.coefs);
+
+        let monomial = ResiduePoly::<1024, SolinasPrime>::default()
+            .add_constant(SolinasPrime::ONE)
+            .multiply_by_monomial(1500);
+        assert_eq!((&p * monomial).coefs, p.multiply_by_monomial(1500).coefs);
+    }
+
+    #[test]
+    fn test_prime_modulus() {
+        let params = MESSAGE_3;
+        let lwe_sk = lwe_keygen(&params);
+        let glwe_sk = keygen::<1024>(&params);
+        let recoded_sk = glwe_sk.recode();
+        let ksk = compute_ksk::<SolinasPrime>(&recoded_sk, &lwe_sk, &params);
+        let glwe_sk2 = keygen::<1024>(&params);
+        let glwe_ksk = compute_glwe_ksk::<1024, SolinasPrime>(&glwe_sk, &glwe_sk2, &params);
+
+        let msgs: Vec<u8> = (0..1024).map(|_| thread_rng().gen_range(0..16)).collect();
+        let pt = ResiduePoly::<1024, SolinasPrime> {
+            coefs: std::array::from_fn(|i| encode_as(msgs[i], &params)),
+        };
+        let ct = GlweCiphertext::encrypt_poly(&pt, &glwe_sk, params.glwe_std_dev);
+        assert_eq!(decode_poly(&ct.decrypt_poly(&glwe_sk), &params), msgs);
+
+        for i in [0, 1, 511, 1023] {
+            let lwe = ct.sample_extract_at(i);
+            assert_eq!(decode(lwe.clone().decrypt(&recoded_sk), &params), msgs[i]);
+
+            let sum = lwe.keyswitch(&ksk)
+                + LweCiphertext::encrypt(encode_as(3, &params), &lwe_sk, params.lwe_std_dev);
+            assert_eq!(decode(sum.decrypt(&lwe_sk), &params), (msgs[i] + 3) % 16);
+        }
+
+        let ks = ct.keyswitch(&glwe_ksk);
+        assert_eq!(decode_poly(&ks.decrypt_poly(&glwe_sk2), &params), msgs);
+    }
+}
@@ -0,0 +1,391 @@
+use crate::client_key::ClientKey;
+use crate::ggsw::{compute_bsk, compute_ternary_bsk, BootstrappingKey, TernaryBootstrappingKey};
+use crate::glwe::{lut_poly, many_lut_poly, GlweCiphertext};
+use crate::lwe::{compute_ksk, KeySwitchingKey, LweCiphertext, ModSwitchedLweCiphertext};
+use crate::multi_bit::{compute_multi_bit_bsk, MultiBitBootstrappingKey};
+use crate::params::{Params, PbsOrder, SecretKeyDistribution};
+use crate::utils::encode;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Bootstrapping key of a [`ServerKey`], which selects the blind rotation algorithm.
+#[derive(Clone, Serialize, Deserialize)]
+pub enum BlindRotationKey<const N: usize> {
+    /// Encryptions of the bits of the LWE key under the GLWE key.
+    Classic(BootstrappingKey<N>),
+    /// Encryptions of products of groups of bits of the LWE key under the GLWE key.
+    MultiBit(MultiBitBootstrappingKey<N>),
+    /// Encryptions of the positive and negative parts of a ternary LWE key under the GLWE key.
+    Ternary(TernaryBootstrappingKey<N>),
+}
+
+/// Public evaluation keys, generated for the parameter set of a [`ClientKey`].
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ServerKey<const N: usize> {
+    pub params: Params,
+    pub bsk: BlindRotationKey<N>,
+    /// Encryptions of the GLWE key (recoded as a LWE key) under the LWE key.
+    /// It is applied after the blind rotation or before, depending on `params.pbs_order`.
+    pub ksk: KeySwitchingKey,
+}
+
+impl<const N: usize> ServerKey<N> {
+    pub fn new(ck: &ClientKey<N>) -> Self {
+        let bsk = match ck.params.lwe_key_distribution {
+            SecretKeyDistribution::Binary => {
+                BlindRotationKey::Classic(compute_bsk(&ck.lwe_sk, &ck.glwe_sk, &ck.params))
+            }
+            SecretKeyDistribution::Ternary => {
+                BlindRotationKey::Ternary(compute_ternary_bsk(&ck.lwe_sk, &ck.glwe_sk, &ck.params))
+            }
+            SecretKeyDistribution::Gaussian(_) => {
+                panic!("bootstrapping requires a binary or ternary LWE key")
+            }
+        };
+
+        ServerKey {
+            params: ck.params,
+            bsk,
+            ksk: compute_ksk(&ck.glwe_sk.recode(), &ck.lwe_sk, &ck.params),
+        }
+    }
+
+    /// Generates a server key which bootstraps with the multi-bit blind rotation.
+    /// The bootstrapping key holds `2^grouping_factor - 1` GGSW ciphertexts per group of `grouping_factor` bits.
+    /// Panics if `grouping_factor` is not 2 or 3.
+    pub fn new_multi_bit(ck: &ClientKey<N>, grouping_factor: usize) -> Self {
+        assert_eq!(
+            ck.params.lwe_key_distribution,
+            SecretKeyDistribution::Binary,
+            "multi-bit bootstrapping requires a binary LWE key"
+        );
+
+        ServerKey {
+            params: ck.params,
+            bsk: BlindRotationKey::MultiBit(compute_multi_bit_bsk(
+                &ck.lwe_sk,
+                &ck.glwe_sk,
+                &ck.params,
+                grouping_factor,
+            )),
+            ksk: compute_ksk(&ck.glwe_sk.recode(), &ck.lwe_sk, &ck.params),
+        }
+    }
+
+    /// Bootstraps `ct`: modswitch, blind rotation, sample extraction and keyswitch, in the order of `params.pbs_order`.
+    /// The output encrypts the same message as `ct` with a fresh noise.
+    pub fn bootstrap(&self, ct: &LweCiphertext) -> LweCiphertext {
+        let lut = GlweCiphertext::trivial_encrypt_lut_poly(&self.params);
+        self.bootstrap_with(ct, &lut)
+    }
+
+    /// Bootstraps `ct` while evaluating `f` on its message.
+    /// The message of `ct` must not use the padding bit, `f` may return any message of `params`.
+    pub fn apply_lut(&self, ct: &LweCiphertext, f: impl Fn(u8) -> u8) -> LweCiphertext {
+        let lut =
+            GlweCiphertext::trivial_encrypt_lut(&self.params, |m| encode(f(m as u8), &self.params));
+        self.bootstrap_with(ct, &lut)
+    }
+
+    /// Bootstraps `ct` while evaluating `f` on its message, which can use the whole plaintext space (padding bit included).
+    /// `f` may return any element of the plaintext space.
+    ///
+    /// Writing `m = m' + s * p/2` with `m' < p/2`, a first PBS extracts the sign `s` to clear the padding bit of `m'`.
+    /// Then `f(m) = g(m') + (-1)^s * h(m')` with `g = (f(m') + f(m' + p/2)) / 2` evaluated on `m'`,
+    /// and `h = (f(m') - f(m' + p/2)) / 2` evaluated by a negacyclic PBS on `m` itself.
+    pub fn full_domain_bootstrap(&self, ct: &LweCiphertext, f: impl Fn(u8) -> u8) -> LweCiphertext {
+        let half_delta = self.params.delta() / 2;
+        let half_p = self.params.plaintext_modulus() / 2;
+        let f = |m: u64| f(m as u8) as u64;
+
+        // (-1)^s * q/4
+        let sign_lut = GlweCiphertext::trivial_encrypt_lut(&self.params, |_| 1 << 62);
+        let mut sign = self.bootstrap_with(ct, &sign_lut);
+
+        // m - s * p/2, with q/4 - (-1)^s * q/4 = s * q/2
+        sign.body = sign.body.wrapping_sub(1 << 62);
+        let cleared = ct + &sign;
+
+        let g_lut = GlweCiphertext::trivial_encrypt_lut(&self.params, |m| {
+            (f(m) + f(m + half_p)).wrapping_mul(half_delta)
+        });
+        let h_lut = GlweCiphertext::trivial_encrypt_lut(&self.params, |m| {
+            f(m).wrapping_sub(f(m + half_p)).wrapping_mul(half_delta)
+        });
+
+        self.bootstrap_with(&cleared, &g_lut) + self.bootstrap_with(ct, &h_lut)
+    }
+
+    /// Blind rotates `lut` by the phase of `ct`, then extracts the constant coefficient under the key of ciphertexts at rest.
+    fn bootstrap_with(&self, ct: &LweCiphertext, lut: &GlweCiphertext<N>) -> LweCiphertext {
+        self.keyswitch_output(self.blind_rotate(ct, lut).sample_extract())
+    }
+
+    /// Switches a ciphertext at rest to the LWE key, before the blind rotation.
+    fn keyswitch_input(&self, ct: &LweCiphertext) -> LweCiphertext {
+        match self.params.pbs_order {
+            PbsOrder::BootstrapKeyswitch => ct.clone(),
+            PbsOrder::KeyswitchBootstrap => ct.keyswitch(&self.ksk),
+        }
+    }
+
+    /// Switches a sample extracted ciphertext, under the recoded GLWE key, to the key of ciphertexts at rest.
+    fn keyswitch_output(&self, ct: LweCiphertext) -> LweCiphertext {
+        match self.params.pbs_order {
+            PbsOrder::BootstrapKeyswitch => ct.keyswitch(&self.ksk),
+            PbsOrder::KeyswitchBootstrap => ct,
+        }
+    }
+
+    /// Bootstraps `ct` once while evaluating every function of `fs` on its message.
+    /// The message of `ct` must not use the padding bit, the functions may return any message of `params`.
+    ///
+    /// The accumulator `v_0 = Δ/2 * (1 + X + ... + X^{N-1})` is blind rotated once, then multiplied for each `f`
+    /// by the small plaintext polynomial `(1 - X) * F`, `F` being the LUT of `f` on unencoded messages,
+    /// since `v_0 * (1 - X) * F = Δ/2 * (1 - X^N) * F = Δ * F`.
+    pub fn multi_value_bootstrap(
+        &self,
+        ct: &LweCiphertext,
+        fs: &[&dyn Fn(u8) -> u8],
+    ) -> Vec<LweCiphertext> {
+        let mut v_0 = GlweCiphertext::zero(self.params.glwe_dim);
+        v_0.body.coefs = [self.params.delta() / 2; N];
+
+        let acc = self.blind_rotate(ct, &v_0);
+
+        fs.iter()
+            .map(|f| {
+                let lut = lut_poly::<N>(&self.params, |m| f(m as u8) as u64);
+                let v_f = &lut - lut.multiply_by_monomial(1);
+
+                self.keyswitch_output(acc.mul_plain(&v_f).sample_extract())
+            })
+            .collect()
+    }
+
+    /// Bootstraps `ct` once while evaluating every function of `fs` on its message, with LUTs interleaved in a single polynomial.
+    /// The message of `ct` must not use the padding bit, the functions may return any message of `params`.
+    ///
+    /// The LWE ciphertext is modswitched to `2N / ν` then scaled back, `ν` being `fs.len()` rounded up to a power of two,
+    /// so that the blind rotation is by a multiple of `ν`. This trades `log2(ν)` bits of modswitch precision for throughput.
+    /// `ν` must not exceed `N`, and an empty `fs` returns no ciphertext.
+    pub fn many_lut_bootstrap(
+        &self,
+        ct: &LweCiphertext,
+        fs: &[&dyn Fn(u8) -> u8],
+    ) -> Vec<LweCiphertext> {
+        if fs.is_empty() {
+            return Vec::new();
+        }
+
+        let count = fs.len().next_power_of_two();
+        assert!(
+            count <= N,
+            "too many LUTs for a polynomial of size {N}, got {}",
+            fs.len()
+        );
+        let encoded: Vec<_> = fs
+            .iter()
+            .map(|f| move |m: u64| encode(f(m as u8), &self.params))
+            .collect();
+        let interleaved: Vec<&dyn Fn(u64) -> u64> = (0..count)
+            .map(|t| &encoded[t % fs.len()] as &dyn Fn(u64) -> u64)
+            .collect();
+
+        let lut = GlweCiphertext {
+            body: many_lut_poly(&self.params, &interleaved),
+            ..GlweCiphertext::zero(self.params.glwe_dim)
+        };
+
+        let log_two_n = (2 * N).ilog2() as usize;
+        let c = self
+            .keyswitch_input(ct)
+            .modswitch(log_two_n - count.ilog2() as usize)
+            .lift(log_two_n);
+        let blind_rotated_lut = self.blind_rotate_modswitched(c, &lut);
+
+        (0..fs.len())
+            .map(|t| self.keyswitch_output(blind_rotated_lut.sample_extract_at(t)))
+            .collect()
+    }
+
+    /// Modswitches `ct`, a ciphertext at rest, and blind rotates `lut` by its phase,
+    /// with the algorithm selected by the bootstrapping key. The output is encrypted under the GLWE key.
+    pub fn blind_rotate(&self, ct: &LweCiphertext, lut: &GlweCiphertext<N>) -> GlweCiphertext<N> {
+        let c = self.keyswitch_input(ct).modswitch((2 * N).ilog2() as usize);
+        self.blind_rotate_modswitched(c, lut)
+    }
+
+    /// Blind rotates `lut` by the phase of `c`, a modswitched LWE ciphertext (modulus = 2N).
+    fn blind_rotate_modswitched(
+        &self,
+        c: ModSwitchedLweCiphertext,
+        lut: &GlweCiphertext<N>,
+    ) -> GlweCiphertext<N> {
+        match &self.bsk {
+            BlindRotationKey::Classic(bsk) => lut.blind_rotate(c, bsk),
+            BlindRotationKey::MultiBit(bsk) => lut.multi_bit_blind_rotate(c, bsk),
+            BlindRotationKey::Ternary(bsk) => lut.ternary_blind_rotate(c, bsk),
+        }
+    }
+
+    /// Bootstraps every ciphertext of `cts`.
+    /// With the `parallel` feature, ciphertexts are processed in parallel.
+    pub fn bootstrap_many(&self, cts: &[LweCiphertext]) -> Vec<LweCiphertext> {
+        #[cfg(feature = "parallel")]
+        let cts = cts.par_iter();
+        #[cfg(not(feature = "parallel"))]
+        let cts = cts.iter();
+
+        cts.map(|ct| self.bootstrap(ct)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::client_key::ClientKey;
+    use crate::params::{Params, PbsOrder, SecretKeyDistribution, FAST_TEST_INSECURE};
+    use crate::server_key::ServerKey;
+    use crate::utils::decode_bootstrapped;
+
+    #[test]
+    fn test_bootstrap() {
+        let ck = ClientKey::<256>::new(FAST_TEST_INSECURE);
+        let sk = ServerKey::new(&ck);
+
+        for msg in 0..ck.params.message_modulus as u8 {
+            let ct = sk.bootstrap(&ck.encrypt(msg));
+            let pt = decode_bootstrapped(ct.decrypt(&ck.lwe_sk), &ck.params);
+            assert_eq!(msg, pt);
+        }
+    }
+
+    #[test]
+    fn test_bootstrap_many() {
+        let ck = ClientKey::<256>::new(FAST_TEST_INSECURE);
+        let sk = ServerKey::new(&ck);
+
+        let msgs: Vec<u8> = (0..ck.params.message_modulus as u8).collect();
+        let cts: Vec<_> = msgs.iter().map(|msg| ck.encrypt(*msg)).collect();
+
+        for (msg, ct) in msgs.iter().zip(sk.bootstrap_many(&cts)) {
+            let pt = decode_bootstrapped(ct.decrypt(&ck.lwe_sk), &ck.params);
+            assert_eq!(*msg, pt);
+        }
+    }
+
+    #[test]
+    fn test_bootstrap_multi_bit() {
+        let ck = ClientKey::<256>::new(FAST_TEST_INSECURE);
+        let sk = ServerKey::new_multi_bit(&ck, 2);
+
+        for msg in 0..ck.params.message_modulus as u8 {
+            let ct = sk.bootstrap(&ck.encrypt(msg));
+            let pt = decode_bootstrapped(ct.decrypt(&ck.lwe_sk), &ck.params);
+            assert_eq!(msg, pt);
+        }
+    }
+
+    #[test]
+    fn test_bootstrap_ternary_and_gaussian_keys() {
+        let params = Params {
+            lwe_key_distribution: SecretKeyDistribution::Ternary,
+            glwe_key_distribution: SecretKeyDistribution::Gaussian(3.2),
+            ..FAST_TEST_INSECURE
+        };
+        let ck = ClientKey::<256>::new(params);
+        let sk = ServerKey::new(&ck);
+
+        for msg in 0..ck.params.message_modulus as u8 {
+            let ct = sk.bootstrap(&ck.encrypt(msg));
+            let pt = decode_bootstrapped(ct.decrypt(&ck.lwe_sk), &ck.params);
+            assert_eq!(msg, pt);
+        }
+    }
+
+    #[test]
+    fn test_apply_lut() {
+        let ck = ClientKey::<256>::new(FAST_TEST_INSECURE);
+        let sk = ServerKey::new(&ck);
+        let f = |m: u8| (3 * m + 1) % 8;
+
+        for msg in 0..ck.params.message_modulus as u8 {
+            let ct = sk.apply_lut(&ck.encrypt(msg), f);
+            assert_eq!(ck.decrypt(&ct), f(msg));
+        }
+    }
+
+    #[test]
+    fn test_multi_value_bootstrap() {
+        let ck = ClientKey::<256>::new(FAST_TEST_INSECURE);
+        let sk = ServerKey::new(&ck);
+        let fs: [&dyn Fn(u8) -> u8; 3] = [&|m| m, &|m| (m * m) % 8, &|m| 7 - m];
+
+        for msg in 0..ck.params.message_modulus as u8 {
+            let cts = sk.multi_value_bootstrap(&ck.encrypt(msg), &fs);
+            for (f, ct) in fs.iter().zip(&cts) {
+                assert_eq!(ck.decrypt(ct), f(msg));
+            }
+        }
+    }
+
+    #[test]
+    fn test_many_lut_bootstrap() {
+        let ck = ClientKey::<256>::new(FAST_TEST_INSECURE);
+        let sk = ServerKey::new(&ck);
+        let fs: [&dyn Fn(u8) -> u8; 2] = [&|m| (m + 1) % 8, &|m| m / 2];
+
+        for msg in 0..ck.params.message_modulus as u8 {
+            let cts = sk.many_lut_bootstrap(&ck.encrypt(msg), &fs);
+            for (f, ct) in fs.iter().zip(&cts) {
+                assert_eq!(ck.decrypt(ct), f(msg));
+            }
+        }
+
+        assert!(sk.many_lut_bootstrap(&ck.encrypt(1), &[]).is_empty());
+    }
+
+    #[test]
+    fn test_full_domain_bootstrap() {
+        let ck = ClientKey::<256>::new(FAST_TEST_INSECURE);
+        let sk = ServerKey::new(&ck);
+        let p = ck.params.plaintext_modulus() as u8;
+
+        for f in [|m: u8| m, |m: u8| (m * m + 3) % 16, |m: u8| 15 - m] {
+            for msg in 0..p {
+                let ct = sk.full_domain_bootstrap(&ck.encrypt(msg), f);
+                assert_eq!(ck.decrypt(&ct), f(msg));
+            }
+        }
+    }
+
+    #[test]
+    fn test_keyswitch_bootstrap_order() {
+        let params = Params {
+            pbs_order: PbsOrder::KeyswitchBootstrap,
+            ..FAST_TEST_INSECURE
+        };
+        let ck = ClientKey::<256>::new(params);
+        let sk = ServerKey::new(&ck);
+        assert_eq!(ck.encrypt(0).mask.len(), params.glwe_dim * params.poly_size);
+
+        for msg in 0..ck.params.message_modulus as u8 {
+            let ct = sk.bootstrap(&ck.encrypt(msg));
+            assert_eq!(ct.mask.len(), params.glwe_dim * params.poly_size);
+            assert_eq!(ck.decrypt(&ct), msg);
+
+            let ct = sk.apply_lut(&ct, |m| (m + 1) % 8);
+            assert_eq!(ck.decrypt(&ct), (msg + 1) % 8);
+        }
+
+        let fs: [&dyn Fn(u8) -> u8; 2] = [&|m| (m + 1) % 8, &|m| m / 2];
+        for msg in 0..ck.params.message_modulus as u8 {
+            let cts = sk.many_lut_bootstrap(&ck.encrypt(msg), &fs);
+            for (f, ct) in fs.iter().zip(&cts) {
+                assert_eq!(ct.mask.len(), params.glwe_dim * params.poly_size);
+                assert_eq!(ck.decrypt(ct), f(msg));
+            }
+        }
+    }
+}
@@ -0,0 +1,703 @@
+use crate::params::DecompositionParams;
+use crate::poly::ResiduePoly;
+use std::sync::OnceLock;
+
+/// Instruction set used by the `u64` and `u32` kernels. Only backends supported by the running CPU can be obtained,
+/// and all of them give bit-for-bit identical results.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Backend(Isa);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Isa {
+    Scalar,
+    #[cfg(target_arch = "x86_64")]
+    Avx2,
+    #[cfg(target_arch = "x86_64")]
+    Avx512,
+}
+
+impl Backend {
+    /// Portable fallback.
+    pub fn scalar() -> Self {
+        Backend(Isa::Scalar)
+    }
+
+    /// Returns the fastest backend supported by the running CPU, detected once.
+    pub fn detect() -> Self {
+        static DETECTED: OnceLock<Backend> = OnceLock::new();
+        *DETECTED.get_or_init(|| *Self::available().last().unwrap())
+    }
+
+    /// Returns every backend supported by the running CPU, from the slowest to the fastest.
+    #[cfg_attr(not(target_arch = "x86_64"), allow(unused_mut))]
+    pub fn available() -> Vec<Self> {
+        let mut res = vec![Backend(Isa::Scalar)];
+
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("avx2") {
+                res.push(Backend(Isa::Avx2));
+            }
+            if is_x86_feature_detected!("avx512f") && is_x86_feature_detected!("avx512dq") {
+                res.push(Backend(Isa::Avx512));
+            }
+        }
+
+        res
+    }
+
+    pub fn name(self) -> &'static str {
+        match self.0 {
+            Isa::Scalar => "scalar",
+            #[cfg(target_arch = "x86_64")]
+            Isa::Avx2 => "avx2",
+            #[cfg(target_arch = "x86_64")]
+            Isa::Avx512 => "avx512",
+        }
+    }
+}
+
+/// Generates the methods of [`Backend`] dispatching every kernel to its implementation for the instruction set.
+macro_rules! impl_dispatch {
+    ($(fn $name:ident $(<$(const $gen:ident: $gen_ty:ty),*>)? ($($arg:ident: $arg_ty:ty),*) $(-> $ret:ty)?;)*) => {
+        impl Backend {
+            $(
+                pub fn $name $(<$(const $gen: $gen_ty),*>)? (self, $($arg: $arg_ty),*) $(-> $ret)? {
+                    match self.0 {
+                        Isa::Scalar => scalar::$name($($arg),*),
+                        // SAFETY: `Backend` values are only built for instruction sets supported by the CPU
+                        #[cfg(target_arch = "x86_64")]
+                        Isa::Avx2 => unsafe { avx2::$name($($arg),*) },
+                        #[cfg(target_arch = "x86_64")]
+                        Isa::Avx512 => unsafe { avx512::$name($($arg),*) },
+                    }
+                }
+            )*
+        }
+    };
+}
+
+impl_dispatch! {
+    fn add_assign(lhs: &mut [u64], rhs: &[u64]);
+    fn sub_assign(lhs: &mut [u64], rhs: &[u64]);
+    fn neg_assign(a: &mut [u64]);
+    fn add_scaled(acc: &mut [u64], src: &[u64], scalar: u64);
+    fn sub_scaled(acc: &mut [u64], src: &[u64], scalar: u64);
+    fn dot_product(a: &[u64], b: &[u64]) -> u64;
+    fn decompose_poly<const N: usize>(
+        poly: &ResiduePoly<N>,
+        decomp: &DecompositionParams,
+        res: &mut [ResiduePoly<N>]
+    );
+    fn add_assign_u32(lhs: &mut [u32], rhs: &[u32]);
+    fn sub_assign_u32(lhs: &mut [u32], rhs: &[u32]);
+    fn neg_assign_u32(a: &mut [u32]);
+    fn add_scaled_u32(acc: &mut [u32], src: &[u32], scalar: u32);
+    fn sub_scaled_u32(acc: &mut [u32], src: &[u32], scalar: u32);
+    fn decompose_poly_u32<const N: usize>(
+        poly: &ResiduePoly<N, u32>,
+        decomp: &DecompositionParams,
+        res: &mut [ResiduePoly<N, u32>]
+    );
+}
+
+/// Portable kernels, also used as the default implementations of [`crate::torus::UnsignedTorus`].
+pub(crate) mod scalar {
+    use crate::params::DecompositionParams;
+    use crate::poly::ResiduePoly;
+    use crate::torus::UnsignedTorus;
+    use crate::utils::{decompose, MAX_LEVEL};
+
+    // the generic kernels also serve the `u32` entry points of `Backend`
+    pub(crate) use self::{
+        add_assign as add_assign_u32, add_scaled as add_scaled_u32,
+        decompose_poly as decompose_poly_u32, neg_assign as neg_assign_u32,
+        sub_assign as sub_assign_u32, sub_scaled as sub_scaled_u32,
+    };
+
+    pub(crate) fn add_assign<T: UnsignedTorus>(lhs: &mut [T], rhs: &[T]) {
+        for (a, b) in lhs.iter_mut().zip(rhs) {
+            *a = a.wrapping_add(*b);
+        }
+    }
+
+    pub(crate) fn sub_assign<T: UnsignedTorus>(lhs: &mut [T], rhs: &[T]) {
+        for (a, b) in lhs.iter_mut().zip(rhs) {
+            *a = a.wrapping_sub(*b);
+        }
+    }
+
+    pub(crate) fn neg_assign<T: UnsignedTorus>(a: &mut [T]) {
+        for x in a {
+            *x = x.wrapping_neg();
+        }
+    }
+
+    pub(crate) fn add_scaled<T: UnsignedTorus>(acc: &mut [T], src: &[T], scalar: T) {
+        for (a, b) in acc.iter_mut().zip(src) {
+            *a = a.wrapping_add(b.wrapping_mul(scalar));
+        }
+    }
+
+    pub(crate) fn sub_scaled<T: UnsignedTorus>(acc: &mut [T], src: &[T], scalar: T) {
+        for (a, b) in acc.iter_mut().zip(src) {
+            *a = a.wrapping_sub(b.wrapping_mul(scalar));
+        }
+    }
+
+    pub(crate) fn dot_product<T: UnsignedTorus>(a: &[T], b: &[T]) -> T {
+        a.iter()
+            .zip(b)
+            .fold(T::ZERO, |acc, (x, y)| acc.wrapping_add(x.wrapping_mul(*y)))
+    }
+
+    pub(crate) fn decompose_poly<const N: usize, T: UnsignedTorus>(
+        poly: &ResiduePoly<N, T>,
+        decomp: &DecompositionParams,
+        res: &mut [ResiduePoly<N, T>],
+    ) {
+        let mut digits = [T::ZERO; MAX_LEVEL];
+
+        for (i, coef) in poly.coefs.iter().enumerate() {
+            decompose(*coef, decomp, &mut digits);
+            for (d, digit) in res.iter_mut().zip(digits) {
+                d.coefs[i] = digit;
+            }
+        }
+    }
+}
+
+/// Kernels on `$t` coefficients written with the vector helpers `load`, `store`, `splat`, `add`, `sub`, `and`,
+/// `mul`, `shr` and `balance` of the enclosing module, processing `LANES` coefficients at once.
+/// Leftover coefficients go through the scalar kernels.
+#[cfg(target_arch = "x86_64")]
+macro_rules! impl_vector_kernels {
+    ($feature:literal, $t:ty) => {
+        #[target_feature(enable = $feature)]
+        pub(in crate::simd) unsafe fn add_assign(lhs: &mut [$t], rhs: &[$t]) {
+            let len = lhs.len().min(rhs.len());
+            let split = len - len % LANES;
+            for i in (0..split).step_by(LANES) {
+                let sum = add(load(lhs.as_ptr().add(i)), load(rhs.as_ptr().add(i)));
+                store(lhs.as_mut_ptr().add(i), sum);
+            }
+            scalar::add_assign(&mut lhs[split..len], &rhs[split..len]);
+        }
+
+        #[target_feature(enable = $feature)]
+        pub(in crate::simd) unsafe fn sub_assign(lhs: &mut [$t], rhs: &[$t]) {
+            let len = lhs.len().min(rhs.len());
+            let split = len - len % LANES;
+            for i in (0..split).step_by(LANES) {
+                let diff = sub(load(lhs.as_ptr().add(i)), load(rhs.as_ptr().add(i)));
+                store(lhs.as_mut_ptr().add(i), diff);
+            }
+            scalar::sub_assign(&mut lhs[split..len], &rhs[split..len]);
+        }
+
+        #[target_feature(enable = $feature)]
+        pub(in crate::simd) unsafe fn neg_assign(a: &mut [$t]) {
+            let split = a.len() - a.len() % LANES;
+            for i in (0..split).step_by(LANES) {
+                store(
+                    a.as_mut_ptr().add(i),
+                    sub(splat(0), load(a.as_ptr().add(i))),
+                );
+            }
+            scalar::neg_assign(&mut a[split..]);
+        }
+
+        /// Same as [`crate::utils::decompose`] on `LANES` coefficients at once.
+        #[target_feature(enable = $feature)]
+        pub(in crate::simd) unsafe fn decompose_poly<const N: usize>(
+            poly: &ResiduePoly<N, $t>,
+            decomp: &DecompositionParams,
+            res: &mut [ResiduePoly<N, $t>],
+        ) {
+            let base_log = decomp.base_log as $t;
+            let base = splat(1 << base_log);
+            let digit_mask = splat((1 << base_log) - 1);
+            let one = splat(1);
+            let rounding_shift = (<$t>::BITS - 1) as $t - base_log * decomp.level as $t;
+
+            let split = N - N % LANES;
+            for i in (0..split).step_by(LANES) {
+                let mut state = shr(load(poly.coefs.as_ptr().add(i)), rounding_shift);
+                state = shr(add(state, and(state, one)), 1);
+
+                for d in res[..decomp.level].iter_mut().rev() {
+                    let digit = and(state, digit_mask);
+                    let (digit, carried) = balance(digit, shr(state, base_log), base);
+                    state = carried;
+                    store(d.coefs.as_mut_ptr().add(i), digit);
+                }
+            }
+
+            let mut digits = [0; crate::utils::MAX_LEVEL];
+            for i in split..N {
+                crate::utils::decompose(poly.coefs[i], decomp, &mut digits);
+                for (d, digit) in res.iter_mut().zip(digits) {
+                    d.coefs[i] = digit;
+                }
+            }
+        }
+    };
+}
+
+/// Dot product of `u64` slices, written with the same helpers as [`impl_vector_kernels`].
+/// There is no `u32` version as secret keys are always stored as `u64`.
+#[cfg(target_arch = "x86_64")]
+macro_rules! impl_dot_product {
+    ($feature:literal) => {
+        #[target_feature(enable = $feature)]
+        pub(super) unsafe fn dot_product(a: &[u64], b: &[u64]) -> u64 {
+            let len = a.len().min(b.len());
+            let split = len - len % LANES;
+            let mut acc = splat(0);
+            for i in (0..split).step_by(LANES) {
+                acc = add(acc, mul(load(a.as_ptr().add(i)), load(b.as_ptr().add(i))));
+            }
+
+            let mut lanes = [0u64; LANES];
+            store(lanes.as_mut_ptr(), acc);
+            lanes.iter().fold(
+                scalar::dot_product(&a[split..len], &b[split..len]),
+                |sum, x| sum.wrapping_add(*x),
+            )
+        }
+    };
+}
+
+/// Multiply-accumulate kernels on `$t` coefficients, written with the same helpers as [`impl_vector_kernels`].
+#[cfg(target_arch = "x86_64")]
+macro_rules! impl_scaled_kernels {
+    ($feature:literal, $t:ty) => {
+        #[target_feature(enable = $feature)]
+        pub(in crate::simd) unsafe fn add_scaled(acc: &mut [$t], src: &[$t], scalar: $t) {
+            let len = acc.len().min(src.len());
+            let split = len - len % LANES;
+            let s = splat(scalar);
+            for i in (0..split).step_by(LANES) {
+                let res = add(load(acc.as_ptr().add(i)), mul(load(src.as_ptr().add(i)), s));
+                store(acc.as_mut_ptr().add(i), res);
+            }
+            scalar::add_scaled(&mut acc[split..len], &src[split..len], scalar);
+        }
+
+        #[target_feature(enable = $feature)]
+        pub(in crate::simd) unsafe fn sub_scaled(acc: &mut [$t], src: &[$t], scalar: $t) {
+            let len = acc.len().min(src.len());
+            let split = len - len % LANES;
+            let s = splat(scalar);
+            for i in (0..split).step_by(LANES) {
+                let res = sub(load(acc.as_ptr().add(i)), mul(load(src.as_ptr().add(i)), s));
+                store(acc.as_mut_ptr().add(i), res);
+            }
+            scalar::sub_scaled(&mut acc[split..len], &src[split..len], scalar);
+        }
+    };
+}
+
+#[cfg(target_arch = "x86_64")]
+mod avx2 {
+    use crate::params::DecompositionParams;
+    use crate::poly::ResiduePoly;
+    use crate::simd::scalar;
+    use std::arch::x86_64::*;
+
+    const LANES: usize = 4;
+
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn load(ptr: *const u64) -> __m256i {
+        _mm256_loadu_si256(ptr.cast())
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn store(ptr: *mut u64, v: __m256i) {
+        _mm256_storeu_si256(ptr.cast(), v)
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn splat(x: u64) -> __m256i {
+        _mm256_set1_epi64x(x as i64)
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn add(a: __m256i, b: __m256i) -> __m256i {
+        _mm256_add_epi64(a, b)
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn sub(a: __m256i, b: __m256i) -> __m256i {
+        _mm256_sub_epi64(a, b)
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn and(a: __m256i, b: __m256i) -> __m256i {
+        _mm256_and_si256(a, b)
+    }
+
+    /// Low 64 bits of the product, from three 32x32-bit multiplications:
+    /// `a * b = a_lo * b_lo + ((a_hi * b_lo + a_lo * b_hi) << 32) mod 2^64`.
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn mul(a: __m256i, b: __m256i) -> __m256i {
+        let low = _mm256_mul_epu32(a, b);
+        let cross = _mm256_add_epi64(
+            _mm256_mul_epu32(_mm256_srli_epi64::<32>(a), b),
+            _mm256_mul_epu32(a, _mm256_srli_epi64::<32>(b)),
+        );
+        _mm256_add_epi64(low, _mm256_slli_epi64::<32>(cross))
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn shr(a: __m256i, shift: u64) -> __m256i {
+        _mm256_srlv_epi64(a, splat(shift))
+    }
+
+    /// Maps digits in `[B/2, B)` to `[-B/2, 0)`, carrying one to the next digit `state`.
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn balance(digit: __m256i, state: __m256i, base: __m256i) -> (__m256i, __m256i) {
+        // digits are below 2^63, so the signed comparison is fine
+        let half = _mm256_srli_epi64::<1>(base);
+        let is_high = _mm256_cmpgt_epi64(digit, sub(half, splat(1)));
+        (sub(digit, and(is_high, base)), sub(state, is_high))
+    }
+
+    impl_vector_kernels!("avx2", u64);
+    impl_scaled_kernels!("avx2", u64);
+    impl_dot_product!("avx2");
+
+    pub(super) use lanes32::{
+        add_assign as add_assign_u32, add_scaled as add_scaled_u32,
+        decompose_poly as decompose_poly_u32, neg_assign as neg_assign_u32,
+        sub_assign as sub_assign_u32, sub_scaled as sub_scaled_u32,
+    };
+
+    /// Same kernels on eight `u32` lanes.
+    mod lanes32 {
+        use crate::params::DecompositionParams;
+        use crate::poly::ResiduePoly;
+        use crate::simd::scalar;
+        use std::arch::x86_64::*;
+
+        const LANES: usize = 8;
+
+        #[inline]
+        #[target_feature(enable = "avx2")]
+        unsafe fn load(ptr: *const u32) -> __m256i {
+            _mm256_loadu_si256(ptr.cast())
+        }
+
+        #[inline]
+        #[target_feature(enable = "avx2")]
+        unsafe fn store(ptr: *mut u32, v: __m256i) {
+            _mm256_storeu_si256(ptr.cast(), v)
+        }
+
+        #[inline]
+        #[target_feature(enable = "avx2")]
+        unsafe fn splat(x: u32) -> __m256i {
+            _mm256_set1_epi32(x as i32)
+        }
+
+        #[inline]
+        #[target_feature(enable = "avx2")]
+        unsafe fn add(a: __m256i, b: __m256i) -> __m256i {
+            _mm256_add_epi32(a, b)
+        }
+
+        #[inline]
+        #[target_feature(enable = "avx2")]
+        unsafe fn sub(a: __m256i, b: __m256i) -> __m256i {
+            _mm256_sub_epi32(a, b)
+        }
+
+        #[inline]
+        #[target_feature(enable = "avx2")]
+        unsafe fn and(a: __m256i, b: __m256i) -> __m256i {
+            _mm256_and_si256(a, b)
+        }
+
+        #[inline]
+        #[target_feature(enable = "avx2")]
+        unsafe fn mul(a: __m256i, b: __m256i) -> __m256i {
+            _mm256_mullo_epi32(a, b)
+        }
+
+        #[inline]
+        #[target_feature(enable = "avx2")]
+        unsafe fn shr(a: __m256i, shift: u32) -> __m256i {
+            _mm256_srlv_epi32(a, splat(shift))
+        }
+
+        /// Maps digits in `[B/2, B)` to `[-B/2, 0)`, carrying one to the next digit `state`.
+        #[inline]
+        #[target_feature(enable = "avx2")]
+        unsafe fn balance(digit: __m256i, state: __m256i, base: __m256i) -> (__m256i, __m256i) {
+            // digits are below 2^31, so the signed comparison is fine
+            let half = _mm256_srli_epi32::<1>(base);
+            let is_high = _mm256_cmpgt_epi32(digit, sub(half, splat(1)));
+            (sub(digit, and(is_high, base)), sub(state, is_high))
+        }
+
+        impl_vector_kernels!("avx2", u32);
+        impl_scaled_kernels!("avx2", u32);
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod avx512 {
+    use crate::params::DecompositionParams;
+    use crate::poly::ResiduePoly;
+    use crate::simd::scalar;
+    use std::arch::x86_64::*;
+
+    const LANES: usize = 8;
+
+    #[inline]
+    #[target_feature(enable = "avx512f")]
+    unsafe fn load(ptr: *const u64) -> __m512i {
+        _mm512_loadu_si512(ptr.cast())
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx512f")]
+    unsafe fn store(ptr: *mut u64, v: __m512i) {
+        _mm512_storeu_si512(ptr.cast(), v)
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx512f")]
+    unsafe fn splat(x: u64) -> __m512i {
+        _mm512_set1_epi64(x as i64)
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx512f")]
+    unsafe fn add(a: __m512i, b: __m512i) -> __m512i {
+        _mm512_add_epi64(a, b)
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx512f")]
+    unsafe fn sub(a: __m512i, b: __m512i) -> __m512i {
+        _mm512_sub_epi64(a, b)
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx512f")]
+    unsafe fn and(a: __m512i, b: __m512i) -> __m512i {
+        _mm512_and_si512(a, b)
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx512f,avx512dq")]
+    unsafe fn mul(a: __m512i, b: __m512i) -> __m512i {
+        _mm512_mullo_epi64(a, b)
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx512f")]
+    unsafe fn shr(a: __m512i, shift: u64) -> __m512i {
+        _mm512_srlv_epi64(a, splat(shift))
+    }
+
+    /// Maps digits in `[B/2, B)` to `[-B/2, 0)`, carrying one to the next digit `state`.
+    #[inline]
+    #[target_feature(enable = "avx512f")]
+    unsafe fn balance(digit: __m512i, state: __m512i, base: __m512i) -> (__m512i, __m512i) {
+        let is_high = _mm512_cmpge_epu64_mask(digit, _mm512_srli_epi64::<1>(base));
+        (
+            _mm512_mask_sub_epi64(digit, is_high, digit, base),
+            _mm512_mask_add_epi64(state, is_high, state, splat(1)),
+        )
+    }
+
+    impl_vector_kernels!("avx512f,avx512dq", u64);
+    impl_scaled_kernels!("avx512f,avx512dq", u64);
+    impl_dot_product!("avx512f,avx512dq");
+
+    pub(super) use lanes32::{
+        add_assign as add_assign_u32, add_scaled as add_scaled_u32,
+        decompose_poly as decompose_poly_u32, neg_assign as neg_assign_u32,
+        sub_assign as sub_assign_u32, sub_scaled as sub_scaled_u32,
+    };
+
+    /// Same kernels on sixteen `u32` lanes.
+    mod lanes32 {
+        use crate::params::DecompositionParams;
+        use crate::poly::ResiduePoly;
+        use crate::simd::scalar;
+        use std::arch::x86_64::*;
+
+        const LANES: usize = 16;
+
+        #[inline]
+        #[target_feature(enable = "avx512f")]
+        unsafe fn load(ptr: *const u32) -> __m512i {
+            _mm512_loadu_si512(ptr.cast())
+        }
+
+        #[inline]
+        #[target_feature(enable = "avx512f")]
+        unsafe fn store(ptr: *mut u32, v: __m512i) {
+            _mm512_storeu_si512(ptr.cast(), v)
+        }
+
+        #[inline]
+        #[target_feature(enable = "avx512f")]
+        unsafe fn splat(x: u32) -> __m512i {
+            _mm512_set1_epi32(x as i32)
+        }
+
+        #[inline]
+        #[target_feature(enable = "avx512f")]
+        unsafe fn add(a: __m512i, b: __m512i) -> __m512i {
+            _mm512_add_epi32(a, b)
+        }
+
+        #[inline]
+        #[target_feature(enable = "avx512f")]
+        unsafe fn sub(a: __m512i, b: __m512i) -> __m512i {
+            _mm512_sub_epi32(a, b)
+        }
+
+        #[inline]
+        #[target_feature(enable = "avx512f")]
+        unsafe fn and(a: __m512i, b: __m512i) -> __m512i {
+            _mm512_and_si512(a, b)
+        }
+
+        #[inline]
+        #[target_feature(enable = "avx512f")]
+        unsafe fn mul(a: __m512i, b: __m512i) -> __m512i {
+            _mm512_mullo_epi32(a, b)
+        }
+
+        #[inline]
+        #[target_feature(enable = "avx512f")]
+        unsafe fn shr(a: __m512i, shift: u32) -> __m512i {
+            _mm512_srlv_epi32(a, splat(shift))
+        }
+
+        /// Maps digits in `[B/2, B)` to `[-B/2, 0)`, carrying one to the next digit `state`.
+        #[inline]
+        #[target_feature(enable = "avx512f")]
+        unsafe fn balance(digit: __m512i, state: __m512i, base: __m512i) -> (__m512i, __m512i) {
+            let is_high = _mm512_cmpge_epu32_mask(digit, _mm512_srli_epi32::<1>(base));
+            (
+                _mm512_mask_sub_epi32(digit, is_high, digit, base),
+                _mm512_mask_add_epi32(state, is_high, state, splat(1)),
+            )
+        }
+
+        impl_vector_kernels!("avx512f", u32);
+        impl_scaled_kernels!("avx512f", u32);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::params::DecompositionParams;
+    use crate::poly::ResiduePoly;
+    use crate::simd::Backend;
+    use crate::torus::UnsignedTorus;
+
+    #[test]
+    fn test_backends_match_scalar() {
+        let scalar = Backend::scalar();
+        // odd lengths exercise the scalar tail of the vector kernels
+        let a: Vec<u64> = (0..1027).map(|_| u64::random()).collect();
+        let b: Vec<u64> = (0..1027).map(|_| u64::random()).collect();
+        let s = u64::random();
+
+        for backend in Backend::available() {
+            let mut expected = a.clone();
+            let mut res = a.clone();
+            scalar.add_assign(&mut expected, &b);
+            backend.add_assign(&mut res, &b);
+            assert_eq!(res, expected, "{}", backend.name());
+
+            scalar.sub_assign(&mut expected, &b);
+            backend.sub_assign(&mut res, &b);
+            scalar.neg_assign(&mut expected);
+            backend.neg_assign(&mut res);
+            scalar.add_scaled(&mut expected, &b, s);
+            backend.add_scaled(&mut res, &b, s);
+            scalar.sub_scaled(&mut expected[3..], &b, s);
+            backend.sub_scaled(&mut res[3..], &b, s);
+            assert_eq!(res, expected, "{}", backend.name());
+
+            assert_eq!(backend.dot_product(&a, &b), scalar.dot_product(&a, &b));
+        }
+    }
+
+    #[test]
+    fn test_u32_backends_match_scalar() {
+        let scalar = Backend::scalar();
+        let a: Vec<u32> = (0..1031).map(|_| u32::random()).collect();
+        let b: Vec<u32> = (0..1031).map(|_| u32::random()).collect();
+        let s = u32::random();
+
+        for backend in Backend::available() {
+            let mut expected = a.clone();
+            let mut res = a.clone();
+            scalar.add_assign_u32(&mut expected, &b);
+            backend.add_assign_u32(&mut res, &b);
+            assert_eq!(res, expected, "{}", backend.name());
+
+            scalar.sub_assign_u32(&mut expected, &b);
+            backend.sub_assign_u32(&mut res, &b);
+            scalar.neg_assign_u32(&mut expected);
+            backend.neg_assign_u32(&mut res);
+            scalar.add_scaled_u32(&mut expected, &b, s);
+            backend.add_scaled_u32(&mut res, &b, s);
+            scalar.sub_scaled_u32(&mut expected[5..], &b, s);
+            backend.sub_scaled_u32(&mut res[5..], &b, s);
+            assert_eq!(res, expected, "{}", backend.name());
+        }
+    }
+
+    #[test]
+    fn test_decompose_backends() {
+        let poly = ResiduePoly::<1024>::get_random();
+
+        for (base_log, level) in [(7, 3), (2, 8), (10, 2), (1, 63)] {
+            let decomp = DecompositionParams { base_log, level };
+            let mut expected = vec![ResiduePoly::default(); level];
+            Backend::scalar().decompose_poly(&poly, &decomp, &mut expected);
+
+            for backend in Backend::available() {
+                let mut res = vec![ResiduePoly::default(); level];
+                backend.decompose_poly(&poly, &decomp, &mut res);
+                for (x, y) in res.iter().zip(&expected) {
+                    assert_eq!(x.coefs, y.coefs, "{}", backend.name());
+                }
+            }
+        }
+
+        let poly = ResiduePoly::<1024, u32>::get_random();
+        for (base_log, level) in [(7, 3), (2, 8), (10, 2), (1, 31)] {
+            let decomp = DecompositionParams { base_log, level };
+            let mut expected = vec![ResiduePoly::default(); level];
+            Backend::scalar().decompose_poly_u32(&poly, &decomp, &mut expected);
+
+            for backend in Backend::available() {
+                let mut res = vec![ResiduePoly::default(); level];
+                backend.decompose_poly_u32(&poly, &decomp, &mut res);
+                for (x, y) in res.iter().zip(&expected) {
+                    assert_eq!(x.coefs, y.coefs, "{}", backend.name());
+                }
+            }
+        }
+    }
+}
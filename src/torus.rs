@@ -0,0 +1,251 @@
+use crate::params::DecompositionParams;
+use crate::poly::ResiduePoly;
+use crate::simd::{scalar, Backend};
+use serde::{de::DeserializeOwned, Serialize};
+use std::fmt::Debug;
+
+/// Element of the discretized torus Z_{q}, stored in an unsigned integer.
+/// q is either `2^BITS` for native integers, or a prime such as [`crate::prime::SolinasPrime`].
+/// All of the arithmetic wraps around modulo q.
+pub trait UnsignedTorus:
+    Copy + Debug + Default + Eq + Send + Sync + Serialize + DeserializeOwned + 'static
+{
+    /// Number of bits of the representation.
+    const BITS: u32;
+    const ZERO: Self;
+    const ONE: Self;
+
+    fn wrapping_add(self, rhs: Self) -> Self;
+    fn wrapping_sub(self, rhs: Self) -> Self;
+    fn wrapping_mul(self, rhs: Self) -> Self;
+    fn wrapping_neg(self) -> Self;
+
+    /// Reduces `x` modulo q.
+    fn from_u64(x: u64) -> Self;
+
+    /// Reduces `x` modulo q, negative values wrapping around.
+    fn from_i64(x: i64) -> Self;
+
+    fn to_u64(self) -> u64;
+
+    /// Returns the representative of `self` in `[-q/2, q/2)`.
+    fn to_i64(self) -> i64;
+
+    /// Samples a uniformly random element of the torus.
+    fn random() -> Self;
+
+    /// Returns q, to scale standard deviations given on the real torus.
+    fn modulus_f64() -> f64;
+
+    /// Returns the element closest to `num * q / 2^log_den`.
+    fn from_fraction(num: u64, log_den: u32) -> Self;
+
+    /// Rounds `self * 2^bits / q` to the nearest integer, in `[0, 2^bits]`, for `bits < Self::BITS`.
+    fn round_to_bits(self, bits: u32) -> u64;
+
+    /// Adds the negacyclic product `lhs * rhs` in Z_{q}\[X\]/(X^N + 1) to `acc`.
+    fn add_negacyclic_mul<const N: usize>(acc: &mut [Self; N], lhs: &[Self; N], rhs: &[Self; N]) {
+        schoolbook_add_mul(acc, lhs, rhs);
+    }
+
+    // Slice kernels, which can be overridden with vectorized implementations.
+
+    /// Computes `lhs += rhs` coefficient-wise.
+    fn add_assign_slice(lhs: &mut [Self], rhs: &[Self]) {
+        scalar::add_assign(lhs, rhs);
+    }
+
+    /// Computes `lhs -= rhs` coefficient-wise.
+    fn sub_assign_slice(lhs: &mut [Self], rhs: &[Self]) {
+        scalar::sub_assign(lhs, rhs);
+    }
+
+    fn neg_assign_slice(a: &mut [Self]) {
+        scalar::neg_assign(a);
+    }
+
+    /// Computes `acc += scalar * src` coefficient-wise.
+    fn add_scaled_slice(acc: &mut [Self], src: &[Self], scalar: Self) {
+        scalar::add_scaled(acc, src, scalar);
+    }
+
+    /// Computes `acc -= scalar * src` coefficient-wise.
+    fn sub_scaled_slice(acc: &mut [Self], src: &[Self], scalar: Self) {
+        scalar::sub_scaled(acc, src, scalar);
+    }
+
+    /// Returns `sum_i a_i * s_i` for the small signed coefficients `s_i` of a secret key.
+    fn key_dot_product(a: &[Self], s: &[u64]) -> Self {
+        a.iter().zip(s).fold(Self::ZERO, |acc, (x, y)| {
+            acc.wrapping_add(x.wrapping_mul(Self::from_i64(*y as i64)))
+        })
+    }
+
+    /// Gadget decomposition of every coefficient of `poly`, the `j`-th digits being written to `res[j]`.
+    fn decompose_poly<const N: usize>(
+        poly: &ResiduePoly<N, Self>,
+        decomp: &DecompositionParams,
+        res: &mut [ResiduePoly<N, Self>],
+    ) {
+        scalar::decompose_poly(poly, decomp, res);
+    }
+}
+
+/// Quadratic implementation of [`UnsignedTorus::add_negacyclic_mul`], without any intermediate polynomial.
+pub(crate) fn schoolbook_add_mul<T: UnsignedTorus, const N: usize>(
+    acc: &mut [T; N],
+    lhs: &[T; N],
+    rhs: &[T; N],
+) {
+    for (i, a) in lhs.iter().enumerate() {
+        // X^i * rhs: the last `i` coefficients wrap around with a minus sign
+        let (low, high) = rhs.split_at(N - i);
+        T::add_scaled_slice(&mut acc[i..], low, *a);
+        T::sub_scaled_slice(&mut acc[..i], high, *a);
+    }
+}
+
+macro_rules! impl_unsigned_torus {
+    ($($t:ty, $signed:ty { $($kernels:item)* });*) => {
+        $(
+            impl UnsignedTorus for $t {
+                const BITS: u32 = <$t>::BITS;
+                const ZERO: Self = 0;
+                const ONE: Self = 1;
+
+                fn wrapping_add(self, rhs: Self) -> Self {
+                    <$t>::wrapping_add(self, rhs)
+                }
+
+                fn wrapping_sub(self, rhs: Self) -> Self {
+                    <$t>::wrapping_sub(self, rhs)
+                }
+
+                fn wrapping_mul(self, rhs: Self) -> Self {
+                    <$t>::wrapping_mul(self, rhs)
+                }
+
+                fn wrapping_neg(self) -> Self {
+                    <$t>::wrapping_neg(self)
+                }
+
+                fn from_u64(x: u64) -> Self {
+                    x as $t
+                }
+
+                fn from_i64(x: i64) -> Self {
+                    x as $t
+                }
+
+                fn to_u64(self) -> u64 {
+                    self as u64
+                }
+
+                fn to_i64(self) -> i64 {
+                    self as $signed as i64
+                }
+
+                fn random() -> Self {
+                    rand::random::<$t>()
+                }
+
+                fn modulus_f64() -> f64 {
+                    f64::powi(2.0, Self::BITS as i32)
+                }
+
+                fn from_fraction(num: u64, log_den: u32) -> Self {
+                    (num as $t) << (Self::BITS - log_den)
+                }
+
+                fn round_to_bits(self, bits: u32) -> u64 {
+                    assert!(bits < Self::BITS, "cannot round to {bits} bits");
+                    // widened so that rounding up the maximum value does not overflow
+                    let rounded_val = (self >> (Self::BITS - 1 - bits)) as u128;
+                    ((rounded_val + (rounded_val & 1)) >> 1) as u64
+                }
+
+                $($kernels)*
+            }
+        )*
+    };
+}
+
+impl_unsigned_torus!(
+    u32, i32 {
+        fn add_assign_slice(lhs: &mut [u32], rhs: &[u32]) {
+            Backend::detect().add_assign_u32(lhs, rhs);
+        }
+
+        fn sub_assign_slice(lhs: &mut [u32], rhs: &[u32]) {
+            Backend::detect().sub_assign_u32(lhs, rhs);
+        }
+
+        fn neg_assign_slice(a: &mut [u32]) {
+            Backend::detect().neg_assign_u32(a);
+        }
+
+        fn add_scaled_slice(acc: &mut [u32], src: &[u32], scalar: u32) {
+            Backend::detect().add_scaled_u32(acc, src, scalar);
+        }
+
+        fn sub_scaled_slice(acc: &mut [u32], src: &[u32], scalar: u32) {
+            Backend::detect().sub_scaled_u32(acc, src, scalar);
+        }
+
+        fn decompose_poly<const N: usize>(
+            poly: &ResiduePoly<N, u32>,
+            decomp: &DecompositionParams,
+            res: &mut [ResiduePoly<N, u32>],
+        ) {
+            Backend::detect().decompose_poly_u32(poly, decomp, res);
+        }
+    };
+    u64, i64 {
+        fn add_assign_slice(lhs: &mut [u64], rhs: &[u64]) {
+            Backend::detect().add_assign(lhs, rhs);
+        }
+
+        fn sub_assign_slice(lhs: &mut [u64], rhs: &[u64]) {
+            Backend::detect().sub_assign(lhs, rhs);
+        }
+
+        fn neg_assign_slice(a: &mut [u64]) {
+            Backend::detect().neg_assign(a);
+        }
+
+        fn add_scaled_slice(acc: &mut [u64], src: &[u64], scalar: u64) {
+            Backend::detect().add_scaled(acc, src, scalar);
+        }
+
+        fn sub_scaled_slice(acc: &mut [u64], src: &[u64], scalar: u64) {
+            Backend::detect().sub_scaled(acc, src, scalar);
+        }
+
+        fn key_dot_product(a: &[u64], s: &[u64]) -> u64 {
+            Backend::detect().dot_product(a, s)
+        }
+
+        fn decompose_poly<const N: usize>(
+            poly: &ResiduePoly<N>,
+            decomp: &DecompositionParams,
+            res: &mut [ResiduePoly<N>],
+        ) {
+            Backend::detect().decompose_poly(poly, decomp, res);
+        }
+    }
+);
+
+#[cfg(test)]
+mod tests {
+    use crate::torus::UnsignedTorus;
+
+    #[test]
+    fn test_round_to_bits_edge_cases() {
+        assert_eq!(u64::MAX.round_to_bits(63), 1 << 63);
+        assert_eq!((u64::MAX - 1).round_to_bits(63), (1 << 63) - 1);
+        assert_eq!(u64::MAX.round_to_bits(0), 1);
+        assert_eq!((1u64 << 62).round_to_bits(0), 0);
+        assert_eq!(u32::MAX.round_to_bits(31), 1 << 31);
+        assert_eq!((3u32 << 29).round_to_bits(2), 2);
+    }
+}
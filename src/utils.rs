@@ -1,22 +1,79 @@
-pub fn encode(msg: u8) -> u64 {
-    (msg as u64) << 60
+use crate::params::{DecompositionParams, Params};
+use crate::poly::ResiduePoly;
+use crate::torus::UnsignedTorus;
+
+pub fn encode(msg: u8, params: &Params) -> u64 {
+    encode_as(msg, params)
+}
+
+/// Encodes `msg` on a torus of `T::BITS` bits, keeping the padding bit.
+pub fn encode_as<T: UnsignedTorus>(msg: u8, params: &Params) -> T {
+    T::from_fraction(msg as u64, params.plaintext_modulus().ilog2())
 }
 
-pub fn decode(mu: u64) -> u8 {
-    ((((mu >> 59) + 1) >> 1) % 16) as u8
+pub fn decode<T: UnsignedTorus>(mu: T, params: &Params) -> u8 {
+    let p = params.plaintext_modulus();
+    (round_value(mu, p.ilog2() as usize) % p) as u8
 }
 
-pub fn decode_bootstrapped(mu: u64) -> u8 {
-    if (mu >> 63) == 1 {
-        decode(!mu) % 8
+pub fn decode_bootstrapped<T: UnsignedTorus>(mu: T, params: &Params) -> u8 {
+    if mu.to_i64() < 0 {
+        decode(mu.wrapping_neg().wrapping_sub(T::ONE), params) % params.message_modulus as u8
     } else {
-        decode(mu) % 8
+        decode(mu, params) % params.message_modulus as u8
+    }
+}
+
+/// Encodes up to `N` messages in the coefficients of a plaintext polynomial.
+/// Missing coefficients are set to zero.
+pub fn encode_poly<const N: usize>(msgs: &[u8], params: &Params) -> ResiduePoly<N> {
+    assert!(
+        msgs.len() <= N,
+        "too many messages for a polynomial of size {N}"
+    );
+
+    let mut res = ResiduePoly::default();
+    for (coef, msg) in res.coefs.iter_mut().zip(msgs) {
+        *coef = encode(*msg, params);
+    }
+    res
+}
+
+/// Decodes every coefficient of a plaintext polynomial.
+pub fn decode_poly<const N: usize, T: UnsignedTorus>(
+    mu: &ResiduePoly<N, T>,
+    params: &Params,
+) -> Vec<u8> {
+    mu.coefs.iter().map(|coef| decode(*coef, params)).collect()
+}
+
+/// Rounds `val` to its `bits` MSBs, returned as an integer in `[0, 2^bits]`.
+pub fn round_value<T: UnsignedTorus>(val: T, bits: usize) -> u64 {
+    val.round_to_bits(bits as u32)
+}
+
+/// Upper bound on the number of levels of a decomposition, for stack-allocated digits.
+pub const MAX_LEVEL: usize = 64;
+
+/// Approximate gadget decomposition of `val` in `level` signed digits in `[-B/2, B/2) as u64`, written to `digits`.
+/// The first digit is the most significant one, i.e. `val ~ sum_j digits[j] * q/B^{j+1}`.
+pub fn decompose<T: UnsignedTorus>(val: T, decomp: &DecompositionParams, digits: &mut [T]) {
+    let base = 1u64 << decomp.base_log;
+    let mut state = round_value(val, decomp.base_log * decomp.level);
+
+    for digit in digits[..decomp.level].iter_mut().rev() {
+        let mut res = state & (base - 1);
+        state >>= decomp.base_log;
+
+        if res >= base >> 1 {
+            res = res.wrapping_sub(base);
+            state += 1;
+        }
+        *digit = T::from_i64(res as i64);
     }
 }
 
-pub fn round_value(val: u64) -> u64 {
-    let mut rounded_val = val >> 47;
-    rounded_val += rounded_val & 1;
-    rounded_val >>= 1;
-    rounded_val
+/// Returns `q/B^{level + 1}`, the gadget vector entry matching the `level`-th digit of [`decompose`].
+pub fn gadget<T: UnsignedTorus>(level: usize, decomp: &DecompositionParams) -> T {
+    T::from_fraction(1, (decomp.base_log * (level + 1)) as u32)
 }
@@ -0,0 +1,81 @@
+use crate::ggsw::{cmux, cmux_tree, GgswCiphertext};
+use crate::glwe::GlweCiphertext;
+use crate::lwe::LweCiphertext;
+use crate::params::Params;
+
+/// Evaluates the lookup table `table` (of torus values) at the index encrypted bit by bit in `bits`,
+/// least significant bit first. The table holds up to `2^bits.len()` entries, missing ones are zero.
+///
+/// The table is stored in the body of trivial GLWE ciphertexts, `N` entries per polynomial.
+/// A CMux tree over the high bits selects the polynomial holding the entry, which is then
+/// blind rotated by the low `log2(N)` bits to bring the entry to the constant coefficient.
+pub fn vertical_packing<const N: usize>(
+    table: &[u64],
+    bits: &[GgswCiphertext<N>],
+    params: &Params,
+) -> LweCiphertext {
+    let log_n = N.ilog2() as usize;
+    let (low, high) = bits.split_at(bits.len().min(log_n));
+    assert!(
+        table.len() <= 1 << bits.len(),
+        "the table has more entries than the index can address"
+    );
+
+    let luts: Vec<GlweCiphertext<N>> = (0..1 << high.len())
+        .map(|t| {
+            let mut lut = GlweCiphertext::zero(params.glwe_dim);
+            for (coef, entry) in lut.body.coefs.iter_mut().zip(table.iter().skip(t * N)) {
+                *coef = *entry;
+            }
+            lut
+        })
+        .collect();
+
+    let mut acc = cmux_tree(high, &luts);
+
+    // X^{-index} puts the coefficient of degree `index` in the constant term
+    for (i, ctb) in low.iter().enumerate() {
+        let rotated = acc.rotate((2 * N - (1 << i)) as u64);
+        acc = cmux(ctb, &acc, &rotated);
+    }
+
+    acc.sample_extract()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ggsw::GgswCiphertext;
+    use crate::glwe::keygen;
+    use crate::params::FAST_TEST_INSECURE;
+    use crate::utils::round_value;
+    use crate::vertical_packing::vertical_packing;
+    use rand::{thread_rng, Rng};
+
+    #[test]
+    fn test_vertical_packing() {
+        let params = FAST_TEST_INSECURE;
+        let sk = keygen::<256>(&params);
+        let recoded_sk = sk.recode();
+
+        // 10-bit to 8-bit table, spread over 4 polynomials
+        let table: Vec<u64> = (0..1024).map(|_| thread_rng().gen_range(0..256)).collect();
+        let encoded: Vec<u64> = table.iter().map(|v| v << 56).collect();
+
+        for _ in 0..10 {
+            let index = thread_rng().gen_range(0..1024);
+            let bits: Vec<GgswCiphertext<256>> = (0..10)
+                .map(|i| {
+                    GgswCiphertext::encrypt(
+                        ((index >> i) & 1) as u8,
+                        &sk,
+                        params.glwe_std_dev,
+                        &params.pbs,
+                    )
+                })
+                .collect();
+
+            let res = vertical_packing(&encoded, &bits, &params).decrypt(&recoded_sk);
+            assert_eq!(round_value(res, 8) % 256, table[index]);
+        }
+    }
+}